@@ -3,7 +3,6 @@ use std::sync::Arc;
 use std::time::{Duration, SystemTime};
 use std::{env, thread};
 
-use nethuns::sockets::errors::NethunsRecvError;
 use nethuns::sockets::{BindableNethunsSocket, PkthdrTrait};
 use nethuns::types::{
     NethunsCaptureDir, NethunsCaptureMode, NethunsQueue, NethunsSocketMode,
@@ -79,11 +78,13 @@ fn main() {
                 
                 if total2 == 10_000_000 {
                     total2 = 0;
-                    socket.dump_rings();
+                    match socket.dump_rings() {
+                        Ok(dump) => println!("{dump:?}"),
+                        Err(e) => eprintln!("[ERROR]: {}", e),
+                    }
                 }
             }
-            Err(NethunsRecvError::NoPacketsAvailable)
-            | Err(NethunsRecvError::PacketFiltered) => {
+            Err(e) if e.is_transient() => {
                 continue;
             }
             Err(e) => {
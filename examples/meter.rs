@@ -6,7 +6,7 @@ use std::time::{Duration, SystemTime};
 
 use etherparse::{IpHeader, PacketHeaders};
 use nethuns::sockets::errors::NethunsRecvError;
-use nethuns::sockets::{BindableNethunsSocket, NethunsSocket};
+use nethuns::sockets::{BindableNethunsSocket, NethunsSocket, RingSlotStatus};
 use nethuns::types::{
     NethunsCaptureDir, NethunsCaptureMode, NethunsQueue, NethunsSocketMode,
     NethunsSocketOptions,
@@ -18,13 +18,13 @@ const HELP_BRIEF: &str = "\
 Usage:  meter [ options ]
 Use --help (or -h) to see full option list and a complete description
 
-Required options:
+Options:
             [ -i <ifname> ]     set network interface
-Other options:
             [ -n <nsock> ]      set number of sockets
             [ -m ]              enable multithreading
             [ -s <sockid> ]     enable per socket stats
             [ -d ]              enable extra debug printing
+            [ -w ]              prewarm the ring buffers before capturing
 ";
 
 const HELP_LONG: &str = "\
@@ -32,11 +32,10 @@ Usage:  send [ options ]
 
 -h, --help                      Show program usage and exit
 
-Required options:
+Options:
 
--i, --interface     <ifname>    Name of the network interface that send operates on.
-
-Other options:
+-i, --interface     <ifname>    Name of the network interface that send operates on
+                                (default = interface of the default route).
 
 -n, --sockets       <nsock>     Number of sockets to use. By default, only one socket is used.
 
@@ -48,6 +47,9 @@ Other options:
 
 -d, --debug                     Enable printing of extra info out to stdout for debug purposes
                                 (e.g., IP address fields of received packets).
+
+-w, --prewarm                   Touch all the ring buffers before starting the capture,
+                                to avoid page faults on the first received packets.
 ";
 
 
@@ -58,6 +60,7 @@ struct Configuration {
     multithreading: bool,
     sockstats: Option<u32>,
     debug: bool,
+    prewarm: bool,
 }
 
 
@@ -187,11 +190,15 @@ fn get_configuration() -> Result<Configuration, anyhow::Error> {
     }
     
     let conf = Configuration {
-        interface: args.value_from_str(["-i", "--interface"])?,
+        interface: match args.opt_value_from_str(["-i", "--interface"])? {
+            Some(interface) => interface,
+            None => nethuns::default_interface()?,
+        },
         num_sockets: args.value_from_str(["-n", "--sockets"]).unwrap_or(1),
         multithreading: args.contains(["-m", "--multithreading"]),
         sockstats: args.value_from_str(["-s", "--sockstats"]).ok(),
         debug: args.contains(["-d", "--debug"]),
+        prewarm: args.contains(["-w", "--prewarm"]),
     };
     
     // It's up to the caller what to do with the remaining arguments.
@@ -230,29 +237,19 @@ fn setup_rx_ring(
     opt: NethunsSocketOptions,
     sockid: u32,
 ) -> NethunsSocket {
+    let queue =
+        NethunsQueue::from_option((conf.num_sockets > 1).then_some(sockid));
     let socket = BindableNethunsSocket::open(opt)
         .expect("Failed to open nethuns socket")
-        .bind(
-            &conf.interface,
-            if conf.num_sockets > 1 {
-                NethunsQueue::Some(sockid)
-            } else {
-                NethunsQueue::Any
-            },
-        )
+        .bind(&conf.interface, queue)
         .expect("Failed to bind nethuns socket");
     
     if conf.debug {
-        println!(
-            "Thread: {}, bind on {}:{}",
-            sockid,
-            conf.interface,
-            if conf.num_sockets > 1 {
-                sockid as i64
-            } else {
-                -1
-            }
-        );
+        println!("Thread: {sockid}, bind on {}:{queue}", conf.interface);
+    }
+    
+    if conf.prewarm {
+        socket.prewarm();
     }
     
     socket
@@ -364,9 +361,7 @@ fn st_execution(
             match recv_pkt(conf, id, &sock, tot, &mut count_to_dump) {
                 Ok(_) => (),
                 Err(e) => match e.downcast_ref::<NethunsRecvError>() {
-                    Some(NethunsRecvError::InUse)
-                    | Some(NethunsRecvError::NoPacketsAvailable)
-                    | Some(NethunsRecvError::PacketFiltered) => (),
+                    Some(e) if e.is_transient() => (),
                     _ => return Err(e),
                 },
             }
@@ -405,9 +400,7 @@ fn mt_execution(
         ) {
             Ok(_) => (),
             Err(e) => match e.downcast_ref::<NethunsRecvError>() {
-                Some(NethunsRecvError::InUse)
-                | Some(NethunsRecvError::NoPacketsAvailable)
-                | Some(NethunsRecvError::PacketFiltered) => (),
+                Some(e) if e.is_transient() => (),
                 _ => return Err(e),
             },
         }
@@ -446,7 +439,17 @@ fn recv_pkt(
     if *count_to_dump == 10_000_000 {
         // do something periodically
         *count_to_dump = 0;
-        socket.dump_rings();
+        let dump = socket.dump_rings()?;
+        if let Some(rx) = dump.rx {
+            println!(
+                "Thread: {}, rx ring: head {}, tail {}, {} of {} slots in use",
+                sockid,
+                rx.head(),
+                rx.tail(),
+                rx.count(RingSlotStatus::InUse),
+                rx.size()
+            );
+        }
     }
     
     Ok(())
@@ -16,13 +16,13 @@ const HELP_BRIEF: &str = "\
 Usage:  send [ options ]
 Use --help (or -h) to see full option list and a complete description
 
-Required options:
+Options:
             [ -i <ifname> ]     set network interface
-Other options:
             [ -b <batch_sz> ]   set batch size
             [ -n <nsock> ]      set number of sockets
             [ -m ]              enable multithreading
             [ -z ]              enable send zero-copy
+            [ -w ]              prewarm the ring buffers before sending
 ";
 
 const HELP_LONG: &str = "\
@@ -30,11 +30,10 @@ Usage:  send [ options ]
 
 -h, --help                      Show program usage and exit
 
-Required options:
+Options:
 
--i, --interface     <ifname>    Name of the network interface that send operates on.
-
-Other options:
+-i, --interface     <ifname>    Name of the network interface that send operates on
+                                (default = interface of the default route).
 
 -b, --batch_size    <batch_sz>  Batch size for packet transmission (default = 1).
 
@@ -45,6 +44,9 @@ Other options:
                                 each socket is handled by a separated thread.
 
 -z, --zerocopy                  Enable send zero-copy. By default, classic send that requires a copy is used.
+
+-w, --prewarm                   Touch all the ring buffers before starting the transmission,
+                                to avoid page faults on the first sent packets.
 ";
 
 
@@ -55,6 +57,7 @@ struct Args {
     num_sockets: u32,
     multithreading: bool,
     zerocopy: bool,
+    prewarm: bool,
 }
 
 
@@ -192,11 +195,15 @@ fn parse_args() -> Result<Args, anyhow::Error> {
     }
     
     let args = Args {
-        interface: pargs.value_from_str(["-i", "--interface"])?,
+        interface: match pargs.opt_value_from_str(["-i", "--interface"])? {
+            Some(interface) => interface,
+            None => nethuns::default_interface()?,
+        },
         batch_size: pargs.value_from_str(["-b", "--batch_size"]).unwrap_or(1),
         num_sockets: pargs.value_from_str(["-n", "--sockets"]).unwrap_or(1),
         multithreading: pargs.contains(["-m", "--multithreading"]),
         zerocopy: pargs.contains(["-z", "--zerocopy"]),
+        prewarm: pargs.contains(["-w", "--prewarm"]),
     };
     
     // It's up to the caller what to do with the remaining arguments.
@@ -393,13 +400,14 @@ fn fill_tx_ring(
     let socket = BindableNethunsSocket::open(opt)?;
     
     // Bind socket
-    let queue = if args.num_sockets > 1 {
-        NethunsQueue::Some(socket_idx)
-    } else {
-        NethunsQueue::Any
-    };
+    let queue =
+        NethunsQueue::from_option((args.num_sockets > 1).then_some(socket_idx));
     let mut socket = socket.bind(&args.interface, queue).map_err(|(e, _)| e)?;
     
+    if args.prewarm {
+        socket.prewarm();
+    }
+    
     // fill the slots in the tx ring (optimized send only)
     if args.zerocopy {
         let size = socket.txring_get_size().expect("socket not in tx mode");
@@ -469,7 +477,7 @@ fn transmit_c(
 ) -> Result<(), anyhow::Error> {
     // Prepare batch
     for _ in 0..args.batch_size {
-        if let Err(e) = socket.send(payload) {
+        if let Err(e) = socket.send_or_flush(payload) {
             eprintln!("Error in transmission for socket {socket_idx}: {e}");
             break;
         }
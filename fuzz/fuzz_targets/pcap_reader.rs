@@ -0,0 +1,46 @@
+//! Fuzz target for the builtin pcap reader.
+//!
+//! Run with `cargo +nightly fuzz run pcap_reader` from the root of the repository.
+
+#![no_main]
+
+use std::{env, fs, process};
+
+use libfuzzer_sys::fuzz_target;
+use nethuns::sockets::pcap::NethunsSocketPcap;
+use nethuns::types::NethunsSocketOptions;
+
+
+/// Maximum number of packets read for each input
+const MAX_PACKETS: usize = 1024;
+
+
+fuzz_target!(|data: &[u8]| {
+    // `NethunsSocketPcap::open` requires a file name,
+    // so the input must be stored in a temporary file
+    let path =
+        env::temp_dir().join(format!("nethuns-fuzz-{}.pcap", process::id()));
+    fs::write(&path, data).expect("failed to write temporary pcap file");
+    
+    let opt = NethunsSocketOptions {
+        numblocks: 1,
+        numpackets: 16,
+        packetsize: 2048,
+        ..Default::default()
+    };
+    
+    if let Ok(socket) = NethunsSocketPcap::open(
+        opt,
+        path.to_str().expect("invalid temporary path"),
+        false,
+    ) {
+        for _ in 0..MAX_PACKETS {
+            match socket.read() {
+                Ok(packet) => drop(packet),
+                Err(_) => break,
+            }
+        }
+    }
+    
+    let _ = fs::remove_file(&path);
+});
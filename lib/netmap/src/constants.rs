@@ -7,8 +7,16 @@
 //! NIOCTXSYNC, NIOCRXSYNC synchronize tx or rx queues,
 //! whose identity is set in NETMAP_REQ_REGISTER through nr_ringid.
 //! These are non blocking and take no argument.
+//!
+//! # NIOCCTRL
+//! The ioctl command for the control requests (e.g. NETMAP_REQ_REGISTER),
+//! which takes a `struct nmreq_header` as argument.
+
+use crate::bindings::nmreq_header;
 
 /// Sync tx queues
 pub const NIOCTXSYNC: u64 = uapi::_IO('i' as _, 148_u64);
 /// Sync rx queues
 pub const NIOCRXSYNC: u64 = uapi::_IO('i' as _, 149_u64);
+/// Control request
+pub const NIOCCTRL: u64 = uapi::_IOWR::<nmreq_header>('i' as _, 151_u64);
@@ -1,10 +1,15 @@
 use std::ffi::CString;
+use std::mem;
 use std::ops::{Deref, DerefMut};
+use std::os::unix::io::RawFd;
 use std::ptr::NonNull;
 
 use crate::bindings::{
-    nmport_close, nmport_d, nmport_open_desc, nmport_prepare,
+    nmport_close, nmport_d, nmport_mmap, nmport_open_desc, nmport_prepare,
 };
+use crate::constants::NIOCCTRL;
+use crate::macros::{netmap_rxring, netmap_txring};
+use crate::ring::NetmapRing;
 
 /// Safe wrapper for [`nmport_d`]. It describes a netmap port.
 #[derive(Debug)]
@@ -42,6 +47,129 @@ impl NmPortDescriptor {
             }
         }
     }
+    
+    
+    /// Open an initialized port descriptor, registering the port
+    /// on an already opened `/dev/netmap` file descriptor
+    /// (e.g. received from a privileged process through `SCM_RIGHTS`)
+    /// instead of opening a new one.
+    ///
+    /// Equivalent to `nmport_open_desc(self.nmport_d)`, with `fd`
+    /// used in place of the file descriptor opened by `nmport_register`.
+    ///
+    /// On success, the descriptor takes ownership of `fd`, which is closed
+    /// when the descriptor is dropped (unless it's given back with
+    /// [`release_fd`](Self::release_fd)). On failure, `fd` is not closed,
+    /// but it stays registered to the port if the mapping of the memory
+    /// region failed.
+    pub fn open_desc_with_fd(&mut self, fd: RawFd) -> Result<(), String> {
+        if !is_netmap_fd(fd) {
+            return Err(format!("fd {fd} is not a netmap file descriptor"));
+        }
+        
+        // [SAFETY] ok: self.nmport_d is a guaranteed to be non-null
+        let d = unsafe { self.nmport_d.as_mut() };
+        if d.register_done != 0 {
+            return Err("the port is already registered".to_owned());
+        }
+        
+        // Register the port (`nmport_register` without the `open`)
+        if unsafe { libc::ioctl(fd, NIOCCTRL, &mut d.hdr) } < 0 {
+            return Err(format!("{}", errno::errno()));
+        }
+        d.fd = fd;
+        d.register_done = 1;
+        
+        // Map the netmap memory region
+        if unsafe { nmport_mmap(d) } < 0 {
+            let e = format!("{}", errno::errno());
+            // Give the ownership of `fd` back to the caller
+            self.release_fd();
+            return Err(e);
+        }
+        Ok(())
+    }
+    
+    
+    /// Give the ownership of the file descriptor of the port back
+    /// to the caller, so that it's not closed when the descriptor
+    /// is dropped (e.g. after [`open_desc_with_fd`](Self::open_desc_with_fd)).
+    ///
+    /// The memory region is still unmapped when the descriptor is dropped.
+    pub fn release_fd(&mut self) -> RawFd {
+        let fd = mem::replace(&mut self.fd, -1);
+        self.register_done = 0;
+        fd
+    }
+    
+    
+    /// Get the RX ring with index `i`, which must be bound to the port
+    /// (from `first_rx_ring` to `last_rx_ring`).
+    ///
+    /// # Returns
+    /// `None` if the port is not open or the ring pointer is null.
+    pub fn rx_ring(&self, i: u16) -> Option<NetmapRing> {
+        debug_assert!((self.first_rx_ring..=self.last_rx_ring).contains(&i));
+        if self.nifp.is_null() {
+            return None;
+        }
+        // [SAFETY] ok: nifp is non-null and i is the index
+        // of a ring bound to the port
+        NonNull::new(unsafe { netmap_rxring(self.nifp, i as _) })
+            .map(NetmapRing::new)
+    }
+    
+    
+    /// Get the TX ring with index `i`, which must be bound to the port
+    /// (from `first_tx_ring` to `last_tx_ring`).
+    ///
+    /// # Returns
+    /// `None` if the port is not open or the ring pointer is null.
+    pub fn tx_ring(&self, i: u16) -> Option<NetmapRing> {
+        debug_assert!((self.first_tx_ring..=self.last_tx_ring).contains(&i));
+        if self.nifp.is_null() {
+            return None;
+        }
+        // [SAFETY] ok: nifp is non-null and i is the index
+        // of a ring bound to the port
+        NonNull::new(unsafe { netmap_txring(self.nifp, i as _) })
+            .map(NetmapRing::new)
+    }
+    
+    
+    /// Iterate over the RX rings bound to the port
+    /// (from `first_rx_ring` to `last_rx_ring`).
+    ///
+    /// No ring is returned if the port is not open,
+    /// and the rings with a null pointer are skipped.
+    pub fn rx_rings(&self) -> impl Iterator<Item = NetmapRing> + '_ {
+        (self.first_rx_ring..=self.last_rx_ring)
+            .filter_map(move |i| self.rx_ring(i))
+    }
+    
+    
+    /// Iterate over the TX rings bound to the port
+    /// (from `first_tx_ring` to `last_tx_ring`).
+    ///
+    /// No ring is returned if the port is not open,
+    /// and the rings with a null pointer are skipped.
+    /// Use [`tx_ring`](Self::tx_ring) when a missing ring is an error.
+    pub fn tx_rings(&self) -> impl Iterator<Item = NetmapRing> + '_ {
+        (self.first_tx_ring..=self.last_tx_ring)
+            .filter_map(move |i| self.tx_ring(i))
+    }
+}
+
+
+/// Check if `fd` refers to the `/dev/netmap` character device.
+fn is_netmap_fd(fd: RawFd) -> bool {
+    let mut st_fd: libc::stat = unsafe { mem::zeroed() };
+    let mut st_dev: libc::stat = unsafe { mem::zeroed() };
+    unsafe {
+        libc::fstat(fd, &mut st_fd) == 0
+            && libc::stat(b"/dev/netmap\0".as_ptr() as _, &mut st_dev) == 0
+    } && (st_fd.st_mode & libc::S_IFMT) == libc::S_IFCHR
+        && st_fd.st_rdev == st_dev.st_rdev
 }
 
 impl Deref for NmPortDescriptor {
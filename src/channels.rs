@@ -0,0 +1,147 @@
+//! Configuration of the number of RX/TX queues (channels) of a device,
+//! through the ethtool `ETHTOOL_GCHANNELS`/`ETHTOOL_SCHANNELS` requests.
+
+use std::ffi::CString;
+use std::{io, mem};
+
+use getset::CopyGetters;
+use rustix::fd::AsRawFd;
+use rustix::net;
+
+
+// Constants from `linux/ethtool.h`
+const ETHTOOL_GCHANNELS: u32 = 0x0000003c;
+const ETHTOOL_SCHANNELS: u32 = 0x0000003d;
+
+
+/// Number of queues (channels) of a device, along with
+/// the maximum numbers supported by the driver.
+///
+/// Many drivers only support `combined` channels, i.e. queues
+/// which are used both for reception and transmission.
+#[derive(Clone, Copy, CopyGetters, Debug, Default, PartialEq, Eq)]
+#[getset(get_copy = "pub")]
+pub struct Channels {
+    max_rx: u32,
+    max_tx: u32,
+    max_other: u32,
+    max_combined: u32,
+    rx: u32,
+    tx: u32,
+    other: u32,
+    combined: u32,
+}
+
+
+/// `struct ethtool_channels` from `linux/ethtool.h`.
+#[repr(C)]
+#[derive(Default)]
+struct EthtoolChannels {
+    cmd: u32,
+    max_rx: u32,
+    max_tx: u32,
+    max_other: u32,
+    max_combined: u32,
+    rx_count: u32,
+    tx_count: u32,
+    other_count: u32,
+    combined_count: u32,
+}
+
+// Make sure the layout matches the one of the kernel
+static_assertions::assert_eq_size!(EthtoolChannels, [u8; 36]);
+
+impl From<&EthtoolChannels> for Channels {
+    fn from(ch: &EthtoolChannels) -> Self {
+        Self {
+            max_rx: ch.max_rx,
+            max_tx: ch.max_tx,
+            max_other: ch.max_other,
+            max_combined: ch.max_combined,
+            rx: ch.rx_count,
+            tx: ch.tx_count,
+            other: ch.other_count,
+            combined: ch.combined_count,
+        }
+    }
+}
+
+
+/// Get the number of RX/TX queues (channels) of the device `dev`.
+///
+/// # Returns
+/// * `Ok(Channels)` - The current and maximum number of channels.
+/// * `Err(io::Error)` with kind [`io::ErrorKind::Unsupported`] - If the driver doesn't support channel configuration.
+/// * `Err(io::Error)` - If the `ioctl` system call failed.
+pub fn get_channels(dev: &str) -> io::Result<Channels> {
+    let mut ch = EthtoolChannels {
+        cmd: ETHTOOL_GCHANNELS,
+        ..Default::default()
+    };
+    ethtool_ioctl(dev, &mut ch)?;
+    Ok(Channels::from(&ch))
+}
+
+
+/// Set the number of RX and TX queues (channels) of the device `dev`,
+/// e.g. to match the number of capture sockets before binding them.
+///
+/// The other channel counts are left unchanged.
+/// It requires the `CAP_NET_ADMIN` capability.
+///
+/// # Returns
+/// * `Ok(())` - If the channels were configured.
+/// * `Err(io::Error)` with kind [`io::ErrorKind::Unsupported`] - If the driver doesn't support channel configuration.
+/// * `Err(io::Error)` with kind [`io::ErrorKind::PermissionDenied`] - If the process lacks `CAP_NET_ADMIN`.
+/// * `Err(io::Error)` - If the `ioctl` system call failed (e.g. `rx`/`tx` exceed the maximum supported by the driver).
+pub fn set_channels(dev: &str, rx: u32, tx: u32) -> io::Result<()> {
+    let mut ch = EthtoolChannels {
+        cmd: ETHTOOL_GCHANNELS,
+        ..Default::default()
+    };
+    ethtool_ioctl(dev, &mut ch)?;
+    
+    ch.cmd = ETHTOOL_SCHANNELS;
+    ch.rx_count = rx;
+    ch.tx_count = tx;
+    ethtool_ioctl(dev, &mut ch)
+}
+
+
+/// Call `ioctl(SIOCETHTOOL)` on the device with the given request.
+fn ethtool_ioctl(dev: &str, ch: &mut EthtoolChannels) -> io::Result<()> {
+    let devname = CString::new(dev)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let socket =
+        net::socket(net::AddressFamily::INET, net::SocketType::DGRAM, None)?;
+    
+    let mut ifr: libc::ifreq = unsafe { mem::zeroed() };
+    devname
+        .to_bytes()
+        .iter()
+        .take(ifr.ifr_name.len() - 1)
+        .enumerate()
+        .for_each(|(i, c)| {
+            ifr.ifr_name[i] = *c as _;
+        });
+    ifr.ifr_ifru.ifru_data = ch as *mut EthtoolChannels as _;
+    
+    let ret = unsafe {
+        libc::ioctl(socket.as_raw_fd(), libc::SIOCETHTOOL, &mut ifr)
+    };
+    if ret < 0 {
+        let e = io::Error::last_os_error();
+        return Err(match e.raw_os_error() {
+            Some(libc::EOPNOTSUPP) => io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("device {dev} doesn't support channel configuration"),
+            ),
+            Some(libc::EPERM) => io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("cannot configure the channels of device {dev}: CAP_NET_ADMIN is required"),
+            ),
+            _ => e,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Ready-made packet filters, to be set with
+//! [`NethunsSocket::set_filter`](crate::sockets::NethunsSocket::set_filter).
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::sockets::PkthdrTrait;
+use crate::types::NethunsFilter;
+
+
+/// Filter which passes one packet every `n` and discards the others,
+/// for a lightweight statistical sampling of the traffic.
+///
+/// The sampling happens after the capture, so it doesn't reduce
+/// the load of the NIC, but it's cheaper than parsing the packets.
+/// It can be composed with other filters through [`all`] and [`any`].
+///
+/// `n` equal to 0 or 1 passes all the packets.
+pub fn sample(n: u32) -> Box<NethunsFilter> {
+    let n = u64::from(n.max(1));
+    let counter = AtomicU64::new(0);
+    Box::new(move |_: &dyn PkthdrTrait, _: &[u8]| {
+        counter.fetch_add(1, Ordering::Relaxed) % n == 0
+    })
+}
+
+
+/// Filter which passes a packet only if all the `filters` pass it.
+///
+/// The filters are evaluated in order, stopping at the first
+/// which discards the packet.
+pub fn all(filters: Vec<Box<NethunsFilter>>) -> Box<NethunsFilter> {
+    Box::new(move |pkthdr: &dyn PkthdrTrait, pkt: &[u8]| {
+        filters.iter().all(|f| f(pkthdr, pkt))
+    })
+}
+
+
+/// Filter which passes a packet if at least one of the `filters` passes it.
+///
+/// The filters are evaluated in order, stopping at the first
+/// which passes the packet.
+pub fn any(filters: Vec<Box<NethunsFilter>>) -> Box<NethunsFilter> {
+    Box::new(move |pkthdr: &dyn PkthdrTrait, pkt: &[u8]| {
+        filters.iter().any(|f| f(pkthdr, pkt))
+    })
+}
@@ -13,6 +13,9 @@ use once_cell::sync::Lazy;
 #[allow(dead_code)] // disable warnings due to missing implementation of XDP
 pub struct NethunsNetInfo {
     pub promisc_refcnt: i32,
+    /// Number of sockets relying on the device having been brought up
+    /// by nethuns (see the `interface_up` option).
+    pub up_refcnt: i32,
     /// xdp only
     pub xdp_prog_refcnt: i32,
     /// xdp only
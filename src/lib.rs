@@ -1,34 +1,113 @@
+mod channels;
 mod global;
+mod logging;
+
+#[cfg(target_os = "linux")]
+use std::io;
+
+use crate::types::Backend;
 
 // Nethuns public API {
+pub mod filter;
 pub mod misc;
+pub mod packet;
 pub mod sockets;
 pub mod types;
 pub mod vlan;
+
+pub use channels::{get_channels, set_channels, Channels};
+pub use sockets::SocketSet;
 // }
 
 
-/// Set `RLIMIT_MEMLOCK` to infinity at application startup.
+/// Get the version of the nethuns library (e.g. `0.1.2`),
+/// useful for diagnostics and bug reports.
+#[inline(always)]
+pub fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+
+/// Get the I/O framework used by the sockets,
+/// according to the cargo features selected at compile time.
+pub fn active_backend() -> Backend {
+    if cfg!(feature = "netmap") {
+        Backend::Netmap
+    } else if cfg!(feature = "xdp") {
+        Backend::AfXdp
+    } else if cfg!(feature = "tpacket_v3") {
+        Backend::AfPacket
+    } else {
+        Backend::Pcap
+    }
+}
+
+
+/// Get the name of the interface associated with the default route
+/// (e.g. to capture on the main NIC without any configuration),
+/// by parsing `/proc/net/route`.
 ///
-/// This function is automatically called before any application code is execution,
-/// thanks to the [small-ctor crate](https://docs.rs/small_ctor/latest/small_ctor/).
+/// If there are multiple default routes, the one with the lowest
+/// metric is chosen. Only IPv4 routes are considered.
+///
+/// # Returns
+/// * `Ok(String)` - The name of the interface.
+/// * `Err(io::Error)` with kind [`io::ErrorKind::NotFound`] - If there is no default route (e.g. in an isolated network namespace).
+/// * `Err(io::Error)` - If `/proc/net/route` couldn't be read.
+#[cfg(target_os = "linux")]
+pub fn default_interface() -> io::Result<String> {
+    let routes = std::fs::read_to_string("/proc/net/route")?;
+    misc::nethuns_parse_default_route(&routes).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "no default route found")
+    })
+}
+
+
+/// Set `RLIMIT_MEMLOCK` to infinity.
+///
+/// Nethuns needs to lock in memory the buffers shared with the underlying
+/// I/O framework, so the limit must be raised before opening any socket.
+/// Unless the `no-auto-rlimit` feature is enabled, this function is
+/// automatically called at application startup; otherwise, it's up to the
+/// application to call it when ready.
 ///
 /// `CAP_SYS_RESOURCE` capability is required to run this function,
 /// because of the call to [`libc::setrlimit`]
 /// (see [setrlimit(2) - Linux man page](https://linux.die.net/man/2/setrlimit)
 /// for more details).
-/// Since this would mean that we must run the tests with root privileges,
-/// this function is disabled while testing.
+///
+/// # Returns
+/// * `Ok(())` - If the limit was successfully raised.
+/// * `Err(io::Error)` - If the call to `setrlimit` failed.
 #[cfg(target_os = "linux")]
-#[cfg(not(test))]
-#[small_ctor::ctor]
-unsafe fn setrlimit() {
+pub fn raise_memlock_limit() -> io::Result<()> {
     let rlim = libc::rlimit {
         rlim_cur: libc::RLIM_INFINITY,
         rlim_max: libc::RLIM_INFINITY,
     };
-    let ret = libc::setrlimit(libc::RLIMIT_MEMLOCK, &rlim);
+    let ret = unsafe { libc::setrlimit(libc::RLIMIT_MEMLOCK, &rlim) };
     if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+
+/// Set `RLIMIT_MEMLOCK` to infinity at application startup.
+///
+/// This function is automatically called before any application code is execution,
+/// thanks to the [small-ctor crate](https://docs.rs/small_ctor/latest/small_ctor/).
+/// It can be compiled out by enabling the `no-auto-rlimit` feature.
+///
+/// `CAP_SYS_RESOURCE` capability is required to run this function
+/// (see [`raise_memlock_limit`]).
+/// Since this would mean that we must run the tests with root privileges,
+/// this function is disabled while testing.
+#[cfg(target_os = "linux")]
+#[cfg(not(any(test, feature = "no-auto-rlimit")))]
+#[small_ctor::ctor]
+unsafe fn setrlimit() {
+    if raise_memlock_limit().is_err() {
         libc::fprintf(
             libc::fdopen(libc::STDERR_FILENO, "w+".as_ptr() as _) as _,
             "nethuns: setrlimit(RLIMIT_MEMLOCK) \"%s\"\n\0".as_ptr() as _,
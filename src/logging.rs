@@ -0,0 +1,55 @@
+//! Diagnostic messages of the library.
+//!
+//! If the `logging` feature is enabled, the messages are emitted through
+//! the [`log`](https://docs.rs/log) crate (with target `nethuns`),
+//! so that the application can capture, filter or suppress them.
+//! Otherwise, errors, warnings and informational messages are printed
+//! to the standard error, while debug messages are discarded.
+
+
+/// Emit an error message.
+macro_rules! nethuns_error {
+    ($($arg: tt)+) => {{
+        #[cfg(feature = "logging")]
+        ::log::error!(target: "nethuns", $($arg)+);
+        #[cfg(not(feature = "logging"))]
+        eprintln!($($arg)+);
+    }};
+}
+pub(crate) use nethuns_error;
+
+
+/// Emit a warning message.
+macro_rules! nethuns_warn {
+    ($($arg: tt)+) => {{
+        #[cfg(feature = "logging")]
+        ::log::warn!(target: "nethuns", $($arg)+);
+        #[cfg(not(feature = "logging"))]
+        eprintln!($($arg)+);
+    }};
+}
+pub(crate) use nethuns_warn;
+
+
+/// Emit an informational message.
+macro_rules! nethuns_info {
+    ($($arg: tt)+) => {{
+        #[cfg(feature = "logging")]
+        ::log::info!(target: "nethuns", $($arg)+);
+        #[cfg(not(feature = "logging"))]
+        eprintln!($($arg)+);
+    }};
+}
+pub(crate) use nethuns_info;
+
+
+/// Emit a debug message.
+macro_rules! nethuns_debug {
+    ($($arg: tt)+) => {{
+        #[cfg(feature = "logging")]
+        ::log::debug!(target: "nethuns", $($arg)+);
+        #[cfg(not(feature = "logging"))]
+        let _ = format_args!($($arg)+);
+    }};
+}
+pub(crate) use nethuns_debug;
@@ -2,13 +2,16 @@
 
 pub(crate) mod circular_queue;
 
-use std::ffi::CStr;
-use std::mem;
+use std::ffi::{CStr, CString};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use std::{cmp, fs, io, mem, panic, thread};
 
 use rustix::fd::AsRawFd;
 use rustix::net;
 
 use crate::global::{NethunsNetInfo, NETHUNS_GLOBAL};
+use crate::logging::nethuns_info;
 use crate::sockets::NethunsSocket;
 use crate::types::NethunsQueue;
 
@@ -42,6 +45,179 @@ pub fn nethuns_device_name(socket: &NethunsSocket) -> String {
 }
 
 
+/// Get the current soft limit for `RLIMIT_MEMLOCK`.
+///
+/// # Returns
+/// * `Some(u64)` - The maximum number of bytes of memory that may be locked.
+/// * `None` - If the limit is infinity or it couldn't be retrieved.
+pub(crate) fn nethuns_memlock_limit() -> Option<u64> {
+    let mut rlim = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    let ret = unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut rlim) };
+    if ret != 0 || rlim.rlim_cur == libc::RLIM_INFINITY {
+        return None;
+    }
+    Some(rlim.rlim_cur as _)
+}
+
+
+/// Get the name of the interface/device with index `ifindex`.
+///
+/// # Returns
+/// * `Some(String)` - The name of the interface.
+/// * `None` - If `ifindex` doesn't correspond to an existing interface.
+pub(crate) fn nethuns_if_indextoname(ifindex: u32) -> Option<String> {
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+    let ret = unsafe { libc::if_indextoname(ifindex, buf.as_mut_ptr()) };
+    if ret.is_null() {
+        return None;
+    }
+    // [SAFETY] on success, `buf` contains a null-terminated string
+    let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+    name.to_str().ok().map(str::to_owned)
+}
+
+
+/// Get the interface of the default route (with the lowest metric),
+/// from the content of `/proc/net/route`.
+///
+/// # Returns
+/// * `Some(String)` - The name of the interface.
+/// * `None` - If there is no default route.
+pub(crate) fn nethuns_parse_default_route(routes: &str) -> Option<String> {
+    const RTF_UP: u32 = 0x0001;
+    
+    routes
+        .lines()
+        .skip(1) // header
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // Iface, Destination, Gateway, Flags, RefCnt, Use, Metric, Mask
+            if fields.len() < 8
+                || fields[1] != "00000000"
+                || fields[7] != "00000000"
+            {
+                return None;
+            }
+            let flags = u32::from_str_radix(fields[3], 16).ok()?;
+            let metric = fields[6].parse::<u32>().ok()?;
+            (flags & RTF_UP != 0).then_some((metric, fields[0]))
+        })
+        .min_by_key(|&(metric, _)| metric)
+        .map(|(_, iface)| iface.to_owned())
+}
+
+
+/// Get the number of RX queues of a device,
+/// by inspecting the `/sys/class/net/<devname>/queues` directory.
+///
+/// # Arguments
+/// * `devname`: Name of the interface/device.
+///
+/// # Returns
+/// * `Some(u32)` - The number of RX queues of the device.
+/// * `None` - If the information is not available (e.g. for virtual ports).
+pub(crate) fn nethuns_if_num_rx_queues(devname: &str) -> Option<u32> {
+    let num_queues = fs::read_dir(format!("/sys/class/net/{devname}/queues"))
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|e| e.file_name().to_string_lossy().starts_with("rx-"))
+        .count();
+    
+    match num_queues {
+        0 => None,
+        n => u32::try_from(n).ok(),
+    }
+}
+
+
+/// Pin the calling thread to the CPU `cpu` through `sched_setaffinity`.
+///
+/// # Returns
+/// * `Ok(())` - If the thread has been pinned.
+/// * `Err(io::Error)` with kind `InvalidInput` - If `cpu` exceeds the maximum CPU index supported by `cpu_set_t`.
+/// * `Err(io::Error)` - If `sched_setaffinity` failed (e.g. the CPU is offline or not allowed for the process).
+pub(crate) fn nethuns_pin_thread(cpu: usize) -> io::Result<()> {
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("[nethuns_pin_thread] invalid cpu index {cpu}"),
+        ));
+    }
+    
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    unsafe { libc::CPU_SET(cpu, &mut set) };
+    let ret = unsafe {
+        libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+
+/// Get the IRQs of the queue `queue` (or of all the queues, if `None`)
+/// of a device, from the content of `/proc/interrupts`.
+///
+/// The IRQs are recognized by their name, which most drivers set to
+/// `<devname>-<type>-<queue>` (e.g. `eth0-TxRx-3`).
+pub(crate) fn nethuns_parse_queue_irqs(
+    interrupts: &str,
+    devname: &str,
+    queue: Option<u32>,
+) -> Vec<u32> {
+    let prefix = format!("{devname}-");
+    
+    interrupts
+        .lines()
+        .filter_map(|line| {
+            let (irq, rest) = line.split_once(':')?;
+            let irq = irq.trim().parse::<u32>().ok()?;
+            let name = rest.split_whitespace().last()?;
+            if !name.starts_with(&prefix) {
+                return None;
+            }
+            match queue {
+                None => Some(irq),
+                Some(q) => name
+                    .rsplit(['-', '_'])
+                    .next()
+                    .filter(|idx| idx.parse() == Ok(q))
+                    .map(|_| irq),
+            }
+        })
+        .collect()
+}
+
+
+/// Steer the interrupts of the queue `queue` (or of all the queues,
+/// if `None`) of a device to the CPU `cpu`, by writing
+/// `/proc/irq/<irq>/smp_affinity_list`.
+/// It requires root privileges, and `irqbalance` may override it.
+///
+/// # Returns
+/// * `Ok(usize)` - The number of IRQs steered to `cpu` (0 if no IRQ of the queue has been found, e.g. for virtual devices).
+/// * `Err(io::Error)` - If `/proc/interrupts` couldn't be read or the affinity of an IRQ couldn't be set.
+pub(crate) fn nethuns_set_irq_affinity(
+    devname: &str,
+    queue: Option<u32>,
+    cpu: usize,
+) -> io::Result<usize> {
+    let interrupts = fs::read_to_string("/proc/interrupts")?;
+    let irqs = nethuns_parse_queue_irqs(&interrupts, devname, queue);
+    for irq in &irqs {
+        fs::write(
+            format!("/proc/irq/{irq}/smp_affinity_list"),
+            cpu.to_string(),
+        )?;
+    }
+    Ok(irqs.len())
+}
+
+
 /// Set interface in promiscuous mode.
 ///
 /// # Arguments
@@ -96,9 +272,9 @@ pub(crate) fn nethuns_set_if_promisc(devname: &CStr) -> Result<(), String> {
         }
         
         if do_promisc {
-            eprintln!("device {devname:?} promisc mode set");
+            nethuns_info!("device {devname:?} promisc mode set");
         } else {
-            eprintln!("device {devname:?} (already) promisc mode set");
+            nethuns_info!("device {devname:?} (already) promisc mode set");
         }
     };
     
@@ -144,7 +320,7 @@ pub(crate) fn nethuns_clear_if_promisc(devname: &CStr) -> Result<(), String> {
                     "[nethuns_clear_if_promisc] nethuns_ioctl_if failed: {e}"
                 ));
             }
-            eprintln!("device {devname:?} promisc mode unset");
+            nethuns_info!("device {devname:?} promisc mode unset");
         }
     };
     
@@ -152,6 +328,132 @@ pub(crate) fn nethuns_clear_if_promisc(devname: &CStr) -> Result<(), String> {
 }
 
 
+/// Bring the interface up (i.e. set `IFF_UP`), if it isn't already.
+///
+/// Sockets sharing a device brought up by nethuns are reference counted,
+/// so that the device is brought down only when the last of them
+/// calls [`nethuns_clear_if_up`].
+///
+/// # Arguments
+/// * `devname`: Name of the interface/device.
+///
+/// # Returns
+/// * `Ok(true)` - If the device has been brought up by nethuns, so that [`nethuns_clear_if_up`] must be called to restore its state.
+/// * `Ok(false)` - If the device was already up.
+/// * `Err(io::Error)` - If `ioctl` failed (`PermissionDenied` if the process lacks `CAP_NET_ADMIN`).
+pub(crate) fn nethuns_set_if_up(devname: &CStr) -> io::Result<bool> {
+    let flags = nethuns_if_flags(devname)?;
+    
+    let mut mutex_guard = NETHUNS_GLOBAL
+        .lock()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    let info = mutex_guard.entry(devname.to_owned()).or_default();
+    
+    // The device has been brought up by another socket
+    if info.up_refcnt > 0 {
+        info.up_refcnt += 1;
+        return Ok(true);
+    }
+    
+    if (flags & libc::IFF_UP as u32) != 0 {
+        nethuns_info!("device {devname:?} (already) up");
+        return Ok(false);
+    }
+    
+    nethuns_set_if_flags(devname, flags | libc::IFF_UP as u32)?;
+    info.up_refcnt = 1;
+    nethuns_info!("device {devname:?} brought up");
+    Ok(true)
+}
+
+
+/// Bring the interface down, if no other socket relies on it having been
+/// brought up by [`nethuns_set_if_up`].
+///
+/// # Arguments
+/// * `devname`: Name of the interface/device.
+///
+/// # Returns
+/// * `Ok(())` - If the operation was successful.
+/// * `Err(io::Error)` - If `ioctl` failed.
+pub(crate) fn nethuns_clear_if_up(devname: &CStr) -> io::Result<()> {
+    let mut mutex_guard = NETHUNS_GLOBAL
+        .lock()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+    let info = match mutex_guard.get_mut(devname) {
+        Some(info) if info.up_refcnt > 0 => info,
+        _ => return Ok(()),
+    };
+    
+    info.up_refcnt -= 1;
+    if info.up_refcnt == 0 {
+        let flags = nethuns_if_flags(devname)?;
+        nethuns_set_if_flags(devname, flags & !(libc::IFF_UP as u32))?;
+        nethuns_info!("device {devname:?} brought down");
+    }
+    Ok(())
+}
+
+
+/// Move the calling thread into the network namespace `netns`
+/// through `setns(CLONE_NEWNET)`.
+///
+/// # Arguments
+/// * `netns`: Path of the namespace (e.g. `/var/run/netns/blue`).
+///
+/// # Returns
+/// * `Ok(())` - If the thread entered the namespace.
+/// * `Err(io::Error)` - If the namespace couldn't be opened or `setns` failed (`PermissionDenied` if the process lacks `CAP_SYS_ADMIN`), with a descriptive message.
+fn nethuns_setns(netns: &Path) -> io::Result<()> {
+    let setns = || {
+        let file = fs::File::open(netns)?;
+        let ret =
+            unsafe { libc::setns(file.as_raw_fd(), libc::CLONE_NEWNET) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    };
+    
+    setns().map_err(|e| {
+        let hint = if e.kind() == io::ErrorKind::PermissionDenied {
+            " (CAP_SYS_ADMIN is required)"
+        } else {
+            ""
+        };
+        io::Error::new(
+            e.kind(),
+            format!(
+                "couldn't enter network namespace {}: {e}{hint}",
+                netns.display()
+            ),
+        )
+    })
+}
+
+
+/// Run `f` on a temporary thread which enters the network namespace `netns`.
+///
+/// The network namespace is a per-thread attribute, so the namespace
+/// of the calling thread is left untouched.
+/// `f` receives the outcome of the namespace switch, so that it can
+/// handle the error (e.g. by giving back the resources it owns).
+///
+/// # Panics
+/// If `f` panics.
+pub(crate) fn nethuns_run_in_netns<T, F>(netns: &Path, f: F) -> T
+where
+    T: Send,
+    F: FnOnce(io::Result<()>) -> T + Send,
+{
+    thread::scope(|s| {
+        s.spawn(|| f(nethuns_setns(netns)))
+            .join()
+            .unwrap_or_else(|e| panic::resume_unwind(e))
+    })
+}
+
+
 /// Call the `ioctl` system call the either get or set the current flag word
 /// of the device.
 ///
@@ -227,6 +529,130 @@ fn nethuns_ioctl_if(
 }
 
 
+/// Get the current flag word of the device through `SIOCGIFFLAGS`.
+///
+/// Unlike [`nethuns_ioctl_if`], the original OS error is preserved,
+/// so that the caller can tell apart a missing device (`ENODEV`).
+///
+/// # Arguments
+/// * `devname`: Name of the interface/device.
+///
+/// # Returns
+/// * `Ok(u32)` - The flag word of the device.
+/// * `Err(io::Error)` - If the socket couldn't be opened or `ioctl` failed.
+fn nethuns_if_flags(devname: &CStr) -> io::Result<u32> {
+    let socket =
+        net::socket(net::AddressFamily::INET, net::SocketType::DGRAM, None)?;
+    
+    let mut ifr: libc::ifreq = unsafe { mem::zeroed() };
+    devname
+        .to_bytes()
+        .iter()
+        .take(ifr.ifr_name.len() - 1)
+        .enumerate()
+        .for_each(|(i, c)| {
+            ifr.ifr_name[i] = *c as _;
+        });
+    
+    let ret = unsafe {
+        libc::ioctl(socket.as_raw_fd(), libc::SIOCGIFFLAGS, &mut ifr)
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { ifr.ifr_ifru.ifru_flags } as u16 as u32)
+}
+
+
+/// Set the flag word of the device through `SIOCSIFFLAGS`,
+/// preserving the original OS error (see [`nethuns_if_flags`]).
+///
+/// # Arguments
+/// * `devname`: Name of the interface/device.
+/// * `flags`: The new flag word.
+///
+/// # Returns
+/// * `Ok(())` - If the flag word was set.
+/// * `Err(io::Error)` - If the socket couldn't be opened or `ioctl` failed.
+fn nethuns_set_if_flags(devname: &CStr, flags: u32) -> io::Result<()> {
+    let socket =
+        net::socket(net::AddressFamily::INET, net::SocketType::DGRAM, None)?;
+    
+    let mut ifr: libc::ifreq = unsafe { mem::zeroed() };
+    devname
+        .to_bytes()
+        .iter()
+        .take(ifr.ifr_name.len() - 1)
+        .enumerate()
+        .for_each(|(i, c)| {
+            ifr.ifr_name[i] = *c as _;
+        });
+    ifr.ifr_ifru.ifru_flags = flags as _;
+    
+    let ret = unsafe {
+        libc::ioctl(socket.as_raw_fd(), libc::SIOCSIFFLAGS, &mut ifr)
+    };
+    if ret < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+
+/// Wait until the device is up and running
+/// (i.e. both `IFF_UP` and `IFF_RUNNING` are set), by polling its flag word.
+///
+/// # Arguments
+/// * `devname`: Name of the interface/device.
+/// * `timeout`: Maximum amount of time to wait.
+///
+/// # Returns
+/// * `Ok(())` - If the device is up and running.
+/// * `Err(io::Error)` with kind `NotFound` - If the device doesn't exist.
+/// * `Err(io::Error)` with kind `TimedOut` - If the device is not up and running before the timeout expires.
+/// * `Err(io::Error)` - If an unexpected error occurs.
+pub(crate) fn nethuns_wait_link_up(
+    devname: &str,
+    timeout: Duration,
+) -> io::Result<()> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(10);
+    const LINK_UP: u32 = (libc::IFF_UP | libc::IFF_RUNNING) as u32;
+    
+    let c_devname = CString::new(devname)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let deadline = Instant::now() + timeout;
+    
+    loop {
+        let flags = nethuns_if_flags(&c_devname).map_err(|e| {
+            match e.raw_os_error() {
+                Some(libc::ENODEV) | Some(libc::ENXIO) => io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!(
+                        "[nethuns_wait_link_up] device {devname} doesn't exist"
+                    ),
+                ),
+                _ => e,
+            }
+        })?;
+        if flags & LINK_UP == LINK_UP {
+            return Ok(());
+        }
+        
+        let now = Instant::now();
+        if now >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "[nethuns_wait_link_up] device {devname} not up \
+                     after {timeout:?}"
+                ),
+            ));
+        }
+        thread::sleep(cmp::min(POLL_INTERVAL, deadline - now));
+    }
+}
+
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -250,4 +676,47 @@ mod tests {
             "eth0".to_owned(),
         );
     }
+    
+    #[test]
+    fn test_nethuns_wait_link_up_no_device() {
+        let err = nethuns_wait_link_up("nethuns-nodev0", Duration::ZERO)
+            .expect_err("the device should not exist");
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+    
+    #[test]
+    fn test_nethuns_parse_default_route() {
+        let header = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n";
+        let routes = format!(
+            "{header}\
+            eth0\t0000A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n\
+            eth1\t00000000\t0101A8C0\t0003\t0\t0\t600\t00000000\t0\t0\t0\n\
+            wlan0\t00000000\t0102A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n"
+        );
+        assert_eq!(
+            nethuns_parse_default_route(&routes),
+            Some("wlan0".to_owned())
+        );
+        assert_eq!(nethuns_parse_default_route(header), None);
+    }
+    
+    #[test]
+    fn test_nethuns_parse_queue_irqs() {
+        let interrupts = "           CPU0       CPU1\n\
+             0:         36          0   IO-APIC   2-edge      timer\n\
+            45:          0       1234   PCI-MSI 524288-edge      eth0\n\
+            46:        100          0   PCI-MSI 524289-edge      eth0-TxRx-0\n\
+            47:          0        200   PCI-MSI 524290-edge      eth0-TxRx-1\n\
+            48:          0        300   PCI-MSI 524291-edge      eth01-TxRx-1\n\
+           NMI:          0          0   Non-maskable interrupts\n";
+        assert_eq!(
+            nethuns_parse_queue_irqs(interrupts, "eth0", Some(1)),
+            vec![47]
+        );
+        assert_eq!(
+            nethuns_parse_queue_irqs(interrupts, "eth0", None),
+            vec![46, 47]
+        );
+        assert!(nethuns_parse_queue_irqs(interrupts, "eth1", None).is_empty());
+    }
 }
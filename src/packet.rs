@@ -0,0 +1,231 @@
+//! Crafting of L2-L4 packets to be sent through a
+//! [`NethunsSocket`](crate::sockets::NethunsSocket).
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use thiserror::Error;
+
+
+/// Default TTL / hop limit of the IP header.
+const DEFAULT_TTL: u8 = 64;
+
+
+/// Error type for [`PacketBuilder::build`]
+#[derive(Debug, Error)]
+pub enum PacketBuilderError {
+    #[error("[packet_builder] missing IP header")]
+    MissingIpHeader,
+    #[error("[packet_builder] missing transport header")]
+    MissingTransportHeader,
+    #[error("[packet_builder] could not build the packet: {0}")]
+    Error(String),
+}
+
+
+/// Network layer of a [`PacketBuilder`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum IpLayer {
+    V4(Ipv4Addr, Ipv4Addr),
+    V6(Ipv6Addr, Ipv6Addr),
+}
+
+
+/// Transport layer of a [`PacketBuilder`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TransportLayer {
+    Udp {
+        src_port: u16,
+        dst_port: u16,
+    },
+    Tcp {
+        src_port: u16,
+        dst_port: u16,
+        seq: u32,
+        window: u16,
+    },
+}
+
+
+/// Builder of Ethernet + IPv4/IPv6 + UDP/TCP packets,
+/// ready to be passed to [`NethunsSocket::send`](crate::sockets::NethunsSocket::send).
+///
+/// Lengths and checksums of the headers are computed automatically.
+///
+/// # Example
+/// ```
+/// use std::net::Ipv4Addr;
+/// use nethuns::packet::PacketBuilder;
+///
+/// let src_mac = [0xf0, 0xbf, 0x97, 0xe2, 0xff, 0xae];
+/// let packet = PacketBuilder::ethernet(src_mac, [0xff; 6])
+///     .ipv4(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2))
+///     .udp(1234, 5678)
+///     .build(b"hello")
+///     .unwrap();
+/// assert_eq!(packet.len(), 14 + 20 + 8 + 5);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PacketBuilder {
+    src_mac: [u8; 6],
+    dst_mac: [u8; 6],
+    ttl: u8,
+    ip: Option<IpLayer>,
+    transport: Option<TransportLayer>,
+}
+
+
+impl PacketBuilder {
+    /// Start a new packet with an Ethernet II header.
+    pub fn ethernet(src_mac: [u8; 6], dst_mac: [u8; 6]) -> Self {
+        Self {
+            src_mac,
+            dst_mac,
+            ttl: DEFAULT_TTL,
+            ip: None,
+            transport: None,
+        }
+    }
+    
+    /// Add an IPv4 header.
+    pub fn ipv4(mut self, src: Ipv4Addr, dst: Ipv4Addr) -> Self {
+        self.ip = Some(IpLayer::V4(src, dst));
+        self
+    }
+    
+    /// Add an IPv6 header.
+    pub fn ipv6(mut self, src: Ipv6Addr, dst: Ipv6Addr) -> Self {
+        self.ip = Some(IpLayer::V6(src, dst));
+        self
+    }
+    
+    /// Set the TTL (IPv4) or hop limit (IPv6) of the IP header.
+    /// Default: 64.
+    pub fn ttl(mut self, ttl: u8) -> Self {
+        self.ttl = ttl;
+        self
+    }
+    
+    /// Add a UDP header.
+    pub fn udp(mut self, src_port: u16, dst_port: u16) -> Self {
+        self.transport = Some(TransportLayer::Udp { src_port, dst_port });
+        self
+    }
+    
+    /// Add a TCP header.
+    pub fn tcp(
+        mut self,
+        src_port: u16,
+        dst_port: u16,
+        seq: u32,
+        window: u16,
+    ) -> Self {
+        self.transport = Some(TransportLayer::Tcp {
+            src_port,
+            dst_port,
+            seq,
+            window,
+        });
+        self
+    }
+    
+    /// Assemble the packet with the given payload.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - The packet, headers included.
+    /// * `Err(PacketBuilderError::MissingIpHeader)` - If no IP header has been added.
+    /// * `Err(PacketBuilderError::MissingTransportHeader)` - If no UDP/TCP header has been added.
+    /// * `Err(PacketBuilderError::Error)` - If the packet couldn't be built (e.g. the payload is too large).
+    pub fn build(
+        &self,
+        payload: &[u8],
+    ) -> Result<Vec<u8>, PacketBuilderError> {
+        let ip = self.ip.ok_or(PacketBuilderError::MissingIpHeader)?;
+        let transport = self
+            .transport
+            .ok_or(PacketBuilderError::MissingTransportHeader)?;
+        
+        let builder =
+            etherparse::PacketBuilder::ethernet2(self.src_mac, self.dst_mac);
+        let builder = match ip {
+            IpLayer::V4(src, dst) => {
+                builder.ipv4(src.octets(), dst.octets(), self.ttl)
+            }
+            IpLayer::V6(src, dst) => {
+                builder.ipv6(src.octets(), dst.octets(), self.ttl)
+            }
+        };
+        
+        let mut packet = Vec::new();
+        let result = match transport {
+            TransportLayer::Udp { src_port, dst_port } => {
+                let builder = builder.udp(src_port, dst_port);
+                packet.reserve_exact(builder.size(payload.len()));
+                builder.write(&mut packet, payload)
+            }
+            TransportLayer::Tcp {
+                src_port,
+                dst_port,
+                seq,
+                window,
+            } => {
+                let builder = builder.tcp(src_port, dst_port, seq, window);
+                packet.reserve_exact(builder.size(payload.len()));
+                builder.write(&mut packet, payload)
+            }
+        };
+        result.map_err(|e| PacketBuilderError::Error(format!("{e:?}")))?;
+        
+        Ok(packet)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use etherparse::{InternetSlice, SlicedPacket, TransportSlice};
+    
+    use super::*;
+    
+    #[test]
+    fn test_packet_builder_udp_ipv4() {
+        let packet = PacketBuilder::ethernet([1; 6], [2; 6])
+            .ipv4(Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2))
+            .udp(1234, 5678)
+            .build(&[0xaa; 10])
+            .unwrap();
+        assert_eq!(packet.len(), 14 + 20 + 8 + 10);
+        
+        let sliced = SlicedPacket::from_ethernet(&packet).unwrap();
+        match sliced.ip {
+            Some(InternetSlice::Ipv4(ip, _)) => {
+                assert_eq!(ip.source_addr(), Ipv4Addr::new(10, 0, 0, 1));
+                assert_eq!(ip.destination_addr(), Ipv4Addr::new(10, 0, 0, 2));
+                assert_eq!(ip.total_len(), 20 + 8 + 10);
+                assert_eq!(ip.ttl(), DEFAULT_TTL);
+            }
+            _ => panic!("expected an IPv4 header"),
+        }
+        match sliced.transport {
+            Some(TransportSlice::Udp(udp)) => {
+                assert_eq!(udp.source_port(), 1234);
+                assert_eq!(udp.destination_port(), 5678);
+                assert_eq!(udp.length(), 8 + 10);
+            }
+            _ => panic!("expected a UDP header"),
+        }
+        assert_eq!(sliced.payload, &[0xaa; 10]);
+    }
+    
+    #[test]
+    fn test_packet_builder_missing_headers() {
+        let builder = PacketBuilder::ethernet([1; 6], [2; 6]);
+        assert!(matches!(
+            builder.build(&[]),
+            Err(PacketBuilderError::MissingIpHeader)
+        ));
+        assert!(matches!(
+            builder.ipv6(Ipv6Addr::LOCALHOST, Ipv6Addr::LOCALHOST).build(&[]),
+            Err(PacketBuilderError::MissingTransportHeader)
+        ));
+    }
+}
@@ -3,30 +3,66 @@
 mod api;
 mod base;
 pub mod errors;
+mod flow_rule;
 pub mod pcap;
+mod profiling;
 mod ring;
+mod socket_set;
 
 pub use api::PkthdrTrait;
-pub use base::RecvPacket;
+pub use base::{
+    by_timestamp, FragmentedPacket, OwnedPacket, PacketBatch, PauseHandle,
+    PeekedPacket, RecvPacket, TxSlotWriter,
+};
+pub use flow_rule::{FlowProto, FlowRule, RuleHandle};
+#[cfg(feature = "profiling")]
+pub use profiling::{TimedOp, TimingHistogram, TimingReport};
+pub use ring::{RingDump, RingKind, RingSlotStatus, RingsDump};
+pub use socket_set::SocketSet;
 
 
-use core::fmt::Debug;
+use core::fmt::{self, Debug};
 use std::cell::UnsafeCell;
 use std::ffi::CStr;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use std::{cmp, hint, io};
 
+use crate::logging::{nethuns_debug, nethuns_warn};
+use crate::misc::{
+    nethuns_dev_queue_name, nethuns_if_indextoname, nethuns_pin_thread,
+    nethuns_run_in_netns, nethuns_set_irq_affinity, nethuns_wait_link_up,
+};
 use crate::types::{
-    NethunsFilter, NethunsQueue, NethunsSocketOptions, NethunsStat,
+    Backend, Capabilities, NethunsFilter, NethunsFilterWithHash,
+    NethunsQueue, NethunsSocketOptions, NethunsStat, NethunsTap, NetmapFlags,
+    ProtoHistogram, TurnoverStats,
 };
 
 use self::api::{
     BindableNethunsSocketInner, BindableNethunsSocketInnerTrait,
     NethunsSocketInner, NethunsSocketInnerTrait,
 };
-use self::base::NethunsSocketBase;
+use self::base::{FragmentState, NethunsSocketBase};
 use self::errors::{
-    NethunsBindError, NethunsFlushError, NethunsOpenError, NethunsRecvError,
-    NethunsSendError,
+    NethunsBindError, NethunsBpfError, NethunsDumpRingsError,
+    NethunsFlowRuleError, NethunsFlushError, NethunsOpenError,
+    NethunsQueueBindingError, NethunsRecvError, NethunsSendError,
 };
+use self::flow_rule::{nethuns_flow_rule_delete, nethuns_flow_rule_insert};
+use self::profiling::nethuns_timed;
+use self::ring::NethunsRing;
+
+
+/// Size of a BPF instruction (`struct sock_filter` for classic BPF,
+/// `struct bpf_insn` for eBPF).
+const BPF_INSN_SIZE: usize = 8;
+
+/// Number of attempts to receive the following fragment of a packet
+/// (see [`NethunsSocket::recv_fragmented`]) before giving up,
+/// while it's not available yet.
+const MAX_FRAGMENT_RETRIES: u32 = 1024;
 
 
 /// Descriptor of a Nethuns socket not binded to a specific device and queue.
@@ -66,23 +102,129 @@ impl BindableNethunsSocket {
     ///
     /// # Returns
     /// * `Ok(())` - If the binding was successful.
-    /// * `Err(NethunsBindError::IllegalArgument)` - If the device name contains an interior null character.
+    /// * `Err(NethunsBindError::IllegalArgument)` - If the device name contains an interior null character, or if the number of extra buffers required by the options overflows.
     /// * `Err(NethunsBindError::FrameworkError)` - If an error from the interaction with underlying I/O framework occurs.
-    /// * `Err(NethunsBindError::Error)` - If an unexpected error occurs.
+    /// * `Err(NethunsBindError::Error)` - If the network namespace set in the `netns` option couldn't be entered, or if an unexpected error occurs.
     #[inline(always)]
     pub fn bind(
         self,
         dev: &str,
         queue: NethunsQueue,
     ) -> Result<NethunsSocket, (NethunsBindError, Self)> {
-        match self.inner.bind(dev, queue) {
+        self.bind_impl(dev, queue, None)
+    }
+    
+    /// Bind an opened socket to a specific queue / any queue of interface/device `dev`,
+    /// using an already opened file descriptor of the I/O framework
+    /// (i.e. `/dev/netmap`) instead of opening a new one.
+    ///
+    /// It enables privilege separation: a privileged helper opens the
+    /// descriptor and hands it (e.g. through `SCM_RIGHTS`)
+    /// to an unprivileged worker, which binds the socket.
+    ///
+    /// On success, the socket takes ownership of `fd`, which is closed
+    /// when the socket is dropped. On failure, `fd` is never closed
+    /// by nethuns, but it may stay registered to the device if the failure
+    /// occurred after the registration (e.g. the promiscuous mode couldn't
+    /// be set): in that case it can't be used to bind another socket
+    /// and should be closed by the caller.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the binding was successful.
+    /// * `Err(NethunsBindError::IllegalArgument)` - If the device name contains an interior null character, or if the number of extra buffers required by the options overflows.
+    /// * `Err(NethunsBindError::FrameworkError)` - If `fd` is not a descriptor of the I/O framework, or if an error from the interaction with underlying I/O framework occurs.
+    /// * `Err(NethunsBindError::Error)` - If the network namespace set in the `netns` option couldn't be entered, or if an unexpected error occurs.
+    #[inline(always)]
+    pub fn bind_with_fd(
+        self,
+        fd: RawFd,
+        dev: &str,
+        queue: NethunsQueue,
+    ) -> Result<NethunsSocket, (NethunsBindError, Self)> {
+        self.bind_impl(dev, queue, Some(fd))
+    }
+    
+    /// Implementation of [`bind`](Self::bind) and [`bind_with_fd`](Self::bind_with_fd).
+    fn bind_impl(
+        self,
+        dev: &str,
+        queue: NethunsQueue,
+        fd: Option<RawFd>,
+    ) -> Result<NethunsSocket, (NethunsBindError, Self)> {
+        let result = match self.inner.base().opt.netns.clone() {
+            None => self.inner.bind(dev, queue, fd),
+            Some(netns) => {
+                let inner = self.inner;
+                nethuns_run_in_netns(&netns, move |entered| match entered {
+                    Ok(()) => inner.bind(dev, queue, fd),
+                    Err(e) => {
+                        Err((NethunsBindError::Error(e.to_string()), inner))
+                    }
+                })
+            }
+        };
+        
+        match result {
             Ok(nethuns_socket_inner) => {
+                nethuns_debug!(
+                    "[bind] socket bound to {}",
+                    nethuns_dev_queue_name(Some(dev), queue)
+                );
                 Ok(NethunsSocket::new(nethuns_socket_inner))
             }
-            Err((err, inner)) => Err((err, Self { inner })),
+            Err((err, inner)) => {
+                nethuns_debug!(
+                    "[bind] couldn't bind socket to {}: {err}",
+                    nethuns_dev_queue_name(Some(dev), queue)
+                );
+                Err((err, Self { inner }))
+            }
+        }
+    }
+    
+    /// Bind an opened socket to a specific queue / any queue of the
+    /// interface/device with index `ifindex`.
+    ///
+    /// The index is resolved to the name of the interface,
+    /// which is then passed to [`BindableNethunsSocket::bind`].
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the binding was successful.
+    /// * `Err(NethunsBindError::IllegalArgument)` - If `ifindex` doesn't correspond to an existing interface.
+    /// * `Err(NethunsBindError::FrameworkError)` - If an error from the interaction with underlying I/O framework occurs.
+    /// * `Err(NethunsBindError::Error)` - If an unexpected error occurs.
+    pub fn bind_by_index(
+        self,
+        ifindex: u32,
+        queue: NethunsQueue,
+    ) -> Result<NethunsSocket, (NethunsBindError, Self)> {
+        match nethuns_if_indextoname(ifindex) {
+            Some(dev) => self.bind(&dev, queue),
+            None => Err((
+                NethunsBindError::IllegalArgument(format!(
+                    "no interface found with index {ifindex}"
+                )),
+                self,
+            )),
         }
     }
     
+    /// Wait until the interface/device `dev` is up and running
+    /// (i.e. both `IFF_UP` and `IFF_RUNNING` are set).
+    ///
+    /// Useful before [`BindableNethunsSocket::bind`] on freshly-created
+    /// interfaces (e.g. veth pairs), whose link may still be coming up.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the device is up and running.
+    /// * `Err(io::Error)` with kind [`io::ErrorKind::NotFound`] - If the device doesn't exist.
+    /// * `Err(io::Error)` with kind [`io::ErrorKind::TimedOut`] - If the device is not up and running before `timeout` expires.
+    /// * `Err(io::Error)` - If an unexpected error occurs.
+    #[inline(always)]
+    pub fn wait_link_up(dev: &str, timeout: Duration) -> io::Result<()> {
+        nethuns_wait_link_up(dev, timeout)
+    }
+    
     delegate::delegate! {
         to self.inner {
             /// Check if the socket is in RX mode
@@ -100,12 +242,21 @@ impl BindableNethunsSocket {
 /// Descriptor of a Nethuns socket after binding.
 ///
 /// This socket is usable for RX and/or TX, depending from its configuration.
-#[derive(Debug)]
 #[repr(transparent)]
 pub struct NethunsSocket {
     inner: UnsafeCell<Box<NethunsSocketInner>>,
 }
 
+/// Print the backend of the socket along with its framework-specific state.
+impl Debug for NethunsSocket {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NethunsSocket")
+            .field("backend", &self.backend())
+            .field("inner", unsafe { &*UnsafeCell::get(&self.inner) })
+            .finish()
+    }
+}
+
 // Make sure BindableNethunsSocket is Send and !Sync
 static_assertions::assert_impl_all!(NethunsSocket: Send);
 static_assertions::assert_not_impl_any!(NethunsSocket: Sync);
@@ -133,12 +284,270 @@ impl NethunsSocket {
     /// * `Err(NethunsRecvError::NotRx)` -  If the socket is not configured in RX mode. Check the configuration parameters passed to [`BindableNethunsSocket::open`].
     /// * `Err(NethunsRecvError::InUse)` - If the slot at the head of the RX ring is currently in use, i.e. the corresponding received packet is not released yet.
     /// * `Err(NethunsRecvError::NoPacketsAvailable)` - If there are no new packets available in the RX ring.
-    /// * `Err(NethunsRecvError::PacketFiltered)` - If the packet is filtered out by the `filter` function specified during socket configuration.
+    /// * `Err(NethunsRecvError::PacketFiltered)` - If the packet is filtered out by the `filter` function specified during socket configuration, or suppressed as a duplicate of the previous one (see the `dedup` option).
+    /// * `Err(NethunsRecvError::FilterPanicked)` - If the `filter` function panicked. The packet is dropped and the filter is removed if the `disable_filter_on_panic` option is set.
     /// * `Err(NethunsRecvError::FrameworkError)` - If an error from the unsafe interaction with underlying I/O framework occurs.
+    /// * `Err(NethunsRecvError::InterfaceGone)` - If the device bound to the socket doesn't exist anymore (see [`NethunsSocket::is_alive`]).
+    /// * `Err(NethunsRecvError::Paused)` - If the capture is paused (see [`NethunsSocket::pause`]).
     /// * `Err(NethunsRecvError::Error)` - If an unexpected error occurs.
     #[inline(always)]
     pub fn recv(&self) -> Result<RecvPacket, NethunsRecvError> {
-        unsafe { (*UnsafeCell::get(&self.inner)).recv() }
+        if self.base().paused.is_paused() {
+            return Err(NethunsRecvError::Paused);
+        }
+        nethuns_timed!(Recv, unsafe {
+            (*UnsafeCell::get(&self.inner)).recv()
+        })
+    }
+    
+    
+    /// Get the next unprocessed received packet,
+    /// waiting at most `timeout` for it to arrive.
+    ///
+    /// See [`NethunsSocket::recv_deadline`].
+    #[inline(always)]
+    pub fn recv_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<RecvPacket, NethunsRecvError> {
+        self.recv_deadline(Instant::now() + timeout)
+    }
+    
+    
+    /// Get the next unprocessed received packet,
+    /// waiting for it to arrive until `deadline`.
+    ///
+    /// The file descriptor of the socket is polled until the deadline,
+    /// so that the thread sleeps while no packets are available.
+    /// Once the deadline has passed, the ring is checked one last time.
+    /// Packets discarded by the filter don't stop the wait, and the thread
+    /// sleeps while the capture is paused (see [`NethunsSocket::pause`]).
+    ///
+    /// # Returns
+    /// * `Ok(RecvPacket)` - The unprocessed received packet, if no error occurred.
+    /// * `Err(NethunsRecvError::NoPacketsAvailable)` - If no packets arrived before the deadline.
+    /// * `Err(NethunsRecvError::Paused)` - If the capture is still paused at the deadline.
+    /// * `Err(NethunsRecvError::FrameworkError)` - If the `poll` system call failed.
+    /// * `Err(NethunsRecvError)` - Any other error returned by [`NethunsSocket::recv`].
+    pub fn recv_deadline(
+        &self,
+        deadline: Instant,
+    ) -> Result<RecvPacket, NethunsRecvError> {
+        loop {
+            match self.recv() {
+                Err(NethunsRecvError::NoPacketsAvailable)
+                | Err(NethunsRecvError::PacketFiltered) => {}
+                Err(NethunsRecvError::Paused) => {
+                    if !self.base().paused.wait_resumed(deadline) {
+                        return Err(NethunsRecvError::Paused);
+                    }
+                    continue;
+                }
+                res => return res,
+            }
+            
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(NethunsRecvError::NoPacketsAvailable);
+            }
+            
+            // Round the remaining time up, so that the deadline
+            // is not missed because of the millisecond resolution of `poll`
+            let remaining_ms = ((deadline - now).as_micros() + 999) / 1000;
+            let mut pfd = libc::pollfd {
+                fd: self.fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let ret = unsafe {
+                libc::poll(
+                    &mut pfd,
+                    1,
+                    cmp::min(remaining_ms, libc::c_int::MAX as u128) as _,
+                )
+            };
+            if ret < 0 {
+                let e = io::Error::last_os_error();
+                if e.kind() != io::ErrorKind::Interrupted {
+                    return Err(NethunsRecvError::FrameworkError(format!(
+                        "poll failed: {e}"
+                    )));
+                }
+            }
+        }
+    }
+    
+    
+    /// Receive packets for the duration `dur`, calling `f` on each of them.
+    ///
+    /// Each packet is released as soon as `f` returns, so that its slot
+    /// can be reused by the ring. Transient errors (see
+    /// [`NethunsRecvError::is_transient`]) don't stop the capture.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use nethuns::sockets::NethunsSocket;
+    /// # fn count(socket: &NethunsSocket) {
+    /// let mut bytes = 0;
+    /// let packets = socket
+    ///     .capture_for(Duration::from_secs(10), |p| {
+    ///         bytes += p.buffer().len();
+    ///     })
+    ///     .unwrap();
+    /// println!("{packets} packets, {bytes} bytes");
+    /// # }
+    /// ```
+    ///
+    /// # Returns
+    /// * `Ok(u64)` - The number of packets passed to `f`, once the duration has elapsed.
+    /// * `Err(NethunsRecvError)` - The first non-transient error returned by [`NethunsSocket::recv_deadline`].
+    pub fn capture_for(
+        &self,
+        dur: Duration,
+        mut f: impl FnMut(&RecvPacket),
+    ) -> Result<u64, NethunsRecvError> {
+        let deadline = Instant::now() + dur;
+        let mut count = 0_u64;
+        
+        loop {
+            match self.recv_deadline(deadline) {
+                Ok(packet) => {
+                    f(&packet);
+                    count += 1;
+                }
+                Err(e) if e.is_transient() => {}
+                Err(e) => return Err(e),
+            }
+            
+            if Instant::now() >= deadline {
+                return Ok(count);
+            }
+        }
+    }
+    
+    
+    /// Get the next unprocessed received packet, copying it into an
+    /// owned buffer and releasing the corresponding ring slot immediately.
+    ///
+    /// It works with any capture mode: the returned packet doesn't borrow
+    /// the socket, so it can be stored or moved to another thread
+    /// without stalling the RX ring, at the cost of a copy.
+    ///
+    /// # Returns
+    /// * `Ok(OwnedPacket)` - The unprocessed received packet, if no error occurred.
+    /// * `Err(NethunsRecvError)` - The same errors of [`NethunsSocket::recv`].
+    pub fn recv_owned(&self) -> Result<OwnedPacket, NethunsRecvError> {
+        let packet = self.recv()?;
+        let id = packet.id();
+        let buffer = packet.buffer().to_vec();
+        // The id of a received packet is the index of its slot plus one
+        let pkthdr = match &self.base().rx_ring {
+            Some(r) => r.get_slot(id.wrapping_sub(1)).pkthdr,
+            None => return Err(NethunsRecvError::NotRx),
+        };
+        drop(packet);
+        Ok(OwnedPacket::new(id, pkthdr, buffer))
+    }
+    
+    
+    /// Get the next unprocessed received packet, along with the index
+    /// of the netmap buffer which contains it.
+    ///
+    /// The buffer index lets external netmap-based tools correlate
+    /// the packet with their own buffer accounting, e.g. in pipelines
+    /// sharing the same netmap buffer pool. It's specific to netmap.
+    ///
+    /// # Returns
+    /// * `Ok((u32, RecvPacket))` - The netmap buffer index and the received packet, if no error occurred.
+    /// * `Err(NethunsRecvError::Error)` - If the packet isn't stored in a netmap buffer, i.e. the socket doesn't use netmap. The packet is released.
+    /// * `Err(NethunsRecvError)` - The same errors of [`NethunsSocket::recv`].
+    pub fn recv_raw_idx(&self) -> Result<(u32, RecvPacket), NethunsRecvError> {
+        let packet = self.recv()?;
+        match packet.pkthdr().netmap_buf_idx() {
+            Some(idx) => Ok((idx, packet)),
+            None => Err(NethunsRecvError::Error(
+                "the packet isn't stored in a netmap buffer".to_owned(),
+            )),
+        }
+    }
+    
+    
+    /// Get the next logical packet, made of all the fragments received
+    /// in consecutive ring slots (e.g. a jumbo frame scattered by netmap
+    /// across multiple slots, each but the last marked with
+    /// [`NetmapFlags::MOREFRAG`](crate::types::NetmapFlags::MOREFRAG)).
+    ///
+    /// The fragment boundaries are preserved. A normal packet is returned
+    /// as a single fragment, so callers have a single code path.
+    /// The filter, the deduplication and the pause of the capture apply
+    /// to the first fragment only.
+    ///
+    /// If a following fragment can't be received, the fragments received
+    /// so far are released and the remaining ones are discarded as they
+    /// arrive, so that they aren't returned later as standalone packets.
+    ///
+    /// # Returns
+    /// * `Ok(FragmentedPacket)` - The fragments of the logical packet, if no error occurred.
+    /// * `Err(NethunsRecvError)` - The same errors of [`NethunsSocket::recv`], when receiving any fragment. `NoPacketsAvailable` is returned only if a following fragment doesn't arrive after some retries.
+    pub fn recv_fragmented(
+        &self,
+    ) -> Result<FragmentedPacket, NethunsRecvError> {
+        let mut fragments = vec![self.recv()?];
+        let mut retries = 0;
+        while fragments
+            .last()
+            .is_some_and(|f| f.netmap_flags().contains(NetmapFlags::MOREFRAG))
+        {
+            // The pause of the capture doesn't split a packet
+            match nethuns_timed!(Recv, unsafe {
+                (*UnsafeCell::get(&self.inner)).recv()
+            }) {
+                Ok(fragment) => {
+                    fragments.push(fragment);
+                    retries = 0;
+                }
+                Err(NethunsRecvError::NoPacketsAvailable)
+                    if retries < MAX_FRAGMENT_RETRIES =>
+                {
+                    retries += 1;
+                    hint::spin_loop();
+                }
+                Err(e) => {
+                    // Resynchronize to the start of the next packet
+                    drop(fragments);
+                    unsafe { (*UnsafeCell::get(&self.inner)).base_mut() }
+                        .fragment = FragmentState::Discard;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(FragmentedPacket::new(fragments))
+    }
+    
+    
+    /// Inspect the next unprocessed received packet without consuming it,
+    /// so that the next call to [`NethunsSocket::recv`] returns the same packet.
+    ///
+    /// The returned [`PeekedPacket`] holds a mutable borrow of the socket,
+    /// which must end before calling [`NethunsSocket::recv`].
+    /// The packet is not checked against the `filter` function.
+    ///
+    /// # Returns
+    /// * `Ok(PeekedPacket)` - The next unprocessed received packet, if no error occurred.
+    /// * `Err(NethunsRecvError::NotRx)` -  If the socket is not configured in RX mode. Check the configuration parameters passed to [`BindableNethunsSocket::open`].
+    /// * `Err(NethunsRecvError::InUse)` - If the slot at the head of the RX ring is currently in use, i.e. the corresponding received packet is not released yet.
+    /// * `Err(NethunsRecvError::NoPacketsAvailable)` - If there are no new packets available in the RX ring.
+    /// * `Err(NethunsRecvError::FrameworkError)` - If an error from the unsafe interaction with underlying I/O framework occurs.
+    /// * `Err(NethunsRecvError::InterfaceGone)` - If the device bound to the socket doesn't exist anymore (see [`NethunsSocket::is_alive`]).
+    /// * `Err(NethunsRecvError::Paused)` - If the capture is paused (see [`NethunsSocket::pause`]).
+    /// * `Err(NethunsRecvError::Error)` - If an unexpected error occurs.
+    #[inline(always)]
+    pub fn recv_peek(&mut self) -> Result<PeekedPacket, NethunsRecvError> {
+        if self.base().paused.is_paused() {
+            return Err(NethunsRecvError::Paused);
+        }
+        UnsafeCell::get_mut(&mut self.inner).recv_peek()
     }
     
     
@@ -151,20 +560,110 @@ impl NethunsSocket {
     /// * `Err(NethunsSendError::InUse)` - If the slot at the tail of the TX ring is not released yet and it's currently in use by the application.
     #[inline(always)]
     pub fn send(&self, packet: &[u8]) -> Result<(), NethunsSendError> {
-        unsafe { (*UnsafeCell::get(&self.inner)).send(packet) }
+        self.send_tracked(packet).map(|_| ())
+    }
+    
+    
+    /// Queue up a packet for transmission, flushing the TX ring
+    /// once if it is full (i.e. the slot at its tail is still in use).
+    ///
+    /// # Returns
+    /// * `Ok(())` - On success.
+    /// * `Err(NethunsSendError::NotTx)` -  If the socket is not configured in TX mode. Check the configuration parameters passed to [`BindableNethunsSocket::open`].
+    /// * `Err(NethunsSendError::InvalidPacketSize)` - If the packet is too large.
+    /// * `Err(NethunsSendError::InUse)` - If the TX ring is still full after the flush.
+    /// * `Err(NethunsSendError::Flush)` - If the flush failed.
+    pub fn send_or_flush(
+        &self,
+        packet: &[u8],
+    ) -> Result<(), NethunsSendError> {
+        match self.send(packet) {
+            Err(NethunsSendError::InUse) => {
+                self.flush()?;
+                self.send(packet)
+            }
+            r => r,
+        }
+    }
+    
+    
+    /// Queue up a packet for transmission and return the id of the
+    /// TX ring slot which contains it.
+    ///
+    /// The status of the slot can be polled with
+    /// [`NethunsSocket::slot_status`] (using [`RingKind::Tx`]) to know when
+    /// the packet has actually been transmitted (i.e. the slot is
+    /// [`RingSlotStatus::Free`] again).
+    ///
+    /// # Returns
+    /// * `Ok(usize)` - The id of the TX ring slot, on success.
+    /// * `Err(NethunsSendError::NotTx)` -  If the socket is not configured in TX mode. Check the configuration parameters passed to [`BindableNethunsSocket::open`].
+    /// * `Err(NethunsSendError::InvalidPacketSize)` - If the packet is too large.
+    /// * `Err(NethunsSendError::InUse)` - If the slot at the tail of the TX ring is not released yet and it's currently in use by the application.
+    #[inline(always)]
+    pub fn send_tracked(
+        &self,
+        packet: &[u8],
+    ) -> Result<usize, NethunsSendError> {
+        nethuns_timed!(Send, unsafe {
+            (*UnsafeCell::get(&self.inner)).send(packet)
+        })
+    }
+    
+    
+    /// Get the current status of a slot of the RX or TX ring.
+    ///
+    /// This is a single atomic load, so it can be cheaply polled in a loop.
+    ///
+    /// # Arguments
+    /// * `ring` - The ring which contains the slot.
+    /// * `id` - The id of the slot, as returned by the socket: for the RX
+    ///   ring, the id of a received packet ([`RecvPacket::id`]), i.e. the
+    ///   index of its slot plus one; for the TX ring, the id returned by
+    ///   [`NethunsSocket::send_tracked`], i.e. the index of the slot.
+    ///   Ids beyond the size of the ring wrap around it.
+    ///
+    /// # Returns
+    /// * `Some(RingSlotStatus)` - The status of the slot.
+    /// * `None` - If the socket doesn't have the requested ring.
+    #[inline(always)]
+    pub fn slot_status(
+        &self,
+        ring: RingKind,
+        id: usize,
+    ) -> Option<RingSlotStatus> {
+        let base = self.base();
+        let (ring, index) = match ring {
+            RingKind::Rx => (base.rx_ring.as_ref()?, id.wrapping_sub(1)),
+            RingKind::Tx => (base.tx_ring.as_ref()?, id),
+        };
+        let slot = ring.get_slot(index % ring.size());
+        Some(slot.status.load(Ordering::Acquire))
     }
     
     
     /// Send all queued up packets.
     ///
+    /// If the `max_tx_batch` option is set, at most that many packets
+    /// are sent by each call, and the others stay queued up:
+    /// their status can be checked with [`NethunsSocket::slot_status`].
+    ///
+    /// When the socket is dropped, the packets still queued up are flushed
+    /// on a best-effort basis (a warning is printed on failure).
+    /// For guaranteed delivery, call this method explicitly
+    /// and check its result before dropping the socket.
+    ///
     /// # Returns
     /// * `Ok(())` - On success.
     /// * `Err(NethunsFlushError::NotTx)` -  If the socket is not configured in TX mode. Check the configuration parameters passed to [`BindableNethunsSocket::open`].
     /// * `Err(NethunsFlushError::FrameworkError)` - If an error from the unsafe interaction with underlying I/O framework occurs.
+    /// * `Err(NethunsFlushError::InterfaceGone)` - If the device bound to the socket doesn't exist anymore (see [`NethunsSocket::is_alive`]).
     /// * `Err(NethunsFlushError::Error)` - If an unexpected error occurs.
     #[inline(always)]
     pub fn flush(&self) -> Result<(), NethunsFlushError> {
-        unsafe { (*UnsafeCell::get(&self.inner)).flush() }
+        nethuns_timed!(Flush, unsafe {
+            (*UnsafeCell::get(&self.inner)).flush()
+        })
     }
     
     
@@ -194,10 +693,246 @@ impl NethunsSocket {
     /// * `filter` - The packet filtering function. `None` if no filtering is required, `Some(filter)` to enable packet filtering.
     #[inline(always)]
     pub fn set_filter(&self, filter: Option<Box<NethunsFilter>>) {
+        self.set_filter_with_hash(filter.map(|f| {
+            let adapter = move |pkthdr: &dyn PkthdrTrait,
+                                pkt: &[u8],
+                                _: Option<u32>| f(pkthdr, pkt);
+            Box::new(adapter) as Box<NethunsFilterWithHash>
+        }));
+    }
+    
+    
+    /// Set the optional packet filtering function,
+    /// which also receives the RSS hash of the packet
+    /// (see [`NethunsFilterWithHash`]).
+    ///
+    /// The hash allows to cheaply bucket flows without parsing the packet.
+    /// Surfacing it costs an additional read of the packet header and
+    /// a branch for each received packet, when the `rxhash` option is set.
+    ///
+    /// # Parameters
+    /// * `filter` - The packet filtering function. `None` if no filtering is required, `Some(filter)` to enable packet filtering.
+    #[inline(always)]
+    pub fn set_filter_with_hash(
+        &self,
+        filter: Option<Box<NethunsFilterWithHash>>,
+    ) {
         unsafe { (*UnsafeCell::get(&self.inner)).base_mut() }.filter = filter;
     }
     
     
+    /// Set the optional tap, which observes the raw bytes
+    /// of every received packet (its whole length, regardless
+    /// of the snaplen), e.g. to log all the traffic
+    /// while only a subset of it is processed.
+    ///
+    /// The tap is called by [`NethunsSocket::recv`], on the receiving
+    /// thread, before the filter: it sees also the packets which are
+    /// filtered out or suppressed as duplicates. It adds a call for each
+    /// received packet to the hot path, so it should be cheap
+    /// (e.g. copy the packet into a ring); without a tap, the cost
+    /// is a single branch. A panic of the tap is caught,
+    /// and the tap is removed.
+    ///
+    /// # Parameters
+    /// * `tap` - The tap closure. `None` to remove the tap.
+    #[inline(always)]
+    pub fn set_tap(&self, tap: Option<Box<NethunsTap>>) {
+        unsafe { (*UnsafeCell::get(&self.inner)).base_mut() }.tap = tap;
+    }
+    
+    
+    /// Program a hardware flow rule on the binded device, which steers
+    /// the matching packets to a specific RX queue
+    /// (ethtool ntuple filter, `ETHTOOL_SRXCLSRLINS`).
+    ///
+    /// This offloads the filtering to the NIC, instead of checking each
+    /// received packet with the software filter (see [`NethunsSocket::set_filter`]).
+    /// The rules still installed are removed when the socket is dropped.
+    ///
+    /// # Returns
+    /// * `Ok(RuleHandle)` - The handle of the installed rule, to be passed to [`NethunsSocket::remove_flow_rule`].
+    /// * `Err(NethunsFlowRuleError::NotSupported)` - If the driver doesn't support ntuple filters (or they are disabled).
+    /// * `Err(NethunsFlowRuleError::NotBound)` - If the socket is not binded to a device.
+    /// * `Err(NethunsFlowRuleError::Error)` - If the rule couldn't be installed.
+    pub fn add_flow_rule(
+        &self,
+        rule: FlowRule,
+    ) -> Result<RuleHandle, NethunsFlowRuleError> {
+        let base = unsafe { (*UnsafeCell::get(&self.inner)).base_mut() };
+        if base.devname.is_empty() {
+            return Err(NethunsFlowRuleError::NotBound);
+        }
+        let handle = match &base.opt.netns {
+            None => nethuns_flow_rule_insert(&base.devname, &rule),
+            Some(netns) => nethuns_run_in_netns(netns, |entered| {
+                entered?;
+                nethuns_flow_rule_insert(&base.devname, &rule)
+            }),
+        }?;
+        base.flow_rules.push(handle);
+        Ok(handle)
+    }
+    
+    
+    /// Attach a BPF program to the socket, so that the packets are filtered
+    /// in-kernel before reaching userspace: a classic BPF program
+    /// (as for `SO_ATTACH_FILTER`) for AF_PACKET, an eBPF program for XDP.
+    ///
+    /// It's much cheaper than checking each received packet with
+    /// the software filter (see [`NethunsSocket::set_filter`]).
+    ///
+    /// # Arguments
+    /// * `prog` - The instructions of the program, in the native byte order.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the program was attached.
+    /// * `Err(NethunsBpfError::InvalidProgram)` - If `prog` is empty or its length is not a multiple of the instruction size (8 bytes).
+    /// * `Err(NethunsBpfError::NotSupported)` - If the I/O framework doesn't support BPF filters (e.g. netmap, see [`NethunsSocket::capabilities`]).
+    /// * `Err(NethunsBpfError::Error)` - If the kernel rejected the program (e.g. verifier error).
+    pub fn attach_bpf(&self, prog: &[u8]) -> Result<(), NethunsBpfError> {
+        if prog.is_empty() || prog.len() % BPF_INSN_SIZE != 0 {
+            return Err(NethunsBpfError::InvalidProgram(format!(
+                "the length ({} bytes) must be a positive multiple of {BPF_INSN_SIZE}",
+                prog.len()
+            )));
+        }
+        unsafe { (*UnsafeCell::get(&self.inner)).attach_bpf(prog) }
+    }
+    
+    
+    /// Detach the BPF program attached with [`NethunsSocket::attach_bpf`].
+    ///
+    /// # Returns
+    /// * `Ok(())` - If no program is attached anymore.
+    /// * `Err(NethunsBpfError::NotSupported)` - If the I/O framework doesn't support BPF filters (e.g. netmap).
+    /// * `Err(NethunsBpfError::Error)` - If the system call failed.
+    #[inline(always)]
+    pub fn detach_bpf(&self) -> Result<(), NethunsBpfError> {
+        unsafe { (*UnsafeCell::get(&self.inner)).detach_bpf() }
+    }
+    
+    
+    /// Remove a hardware flow rule previously installed with
+    /// [`NethunsSocket::add_flow_rule`].
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the rule was removed.
+    /// * `Err(NethunsFlowRuleError::NotSupported)` - If the driver doesn't support ntuple filters.
+    /// * `Err(NethunsFlowRuleError::NotBound)` - If the socket is not binded to a device.
+    /// * `Err(NethunsFlowRuleError::Error)` - If the rule couldn't be removed.
+    pub fn remove_flow_rule(
+        &self,
+        handle: RuleHandle,
+    ) -> Result<(), NethunsFlowRuleError> {
+        let base = unsafe { (*UnsafeCell::get(&self.inner)).base_mut() };
+        if base.devname.is_empty() {
+            return Err(NethunsFlowRuleError::NotBound);
+        }
+        match &base.opt.netns {
+            None => nethuns_flow_rule_delete(&base.devname, handle),
+            Some(netns) => nethuns_run_in_netns(netns, |entered| {
+                entered?;
+                nethuns_flow_rule_delete(&base.devname, handle)
+            }),
+        }?;
+        base.flow_rules.retain(|&h| h != handle);
+        Ok(())
+    }
+    
+    
+    /// Pause the capture, e.g. during a reload of the configuration
+    /// of the application, without unbinding the socket.
+    ///
+    /// While paused, [`NethunsSocket::recv`] returns
+    /// [`NethunsRecvError::Paused`] without draining the RX ring,
+    /// so that the packets are buffered by the NIC and the kernel
+    /// (or dropped once the ring is full).
+    #[inline(always)]
+    pub fn pause(&self) {
+        self.base().paused.pause()
+    }
+    
+    /// Resume the capture paused by [`NethunsSocket::pause`].
+    #[inline(always)]
+    pub fn resume(&self) {
+        self.base().paused.resume()
+    }
+    
+    /// Check if the capture is paused.
+    #[inline(always)]
+    pub fn is_paused(&self) -> bool {
+        self.base().paused.is_paused()
+    }
+    
+    /// Get a handle to pause and resume the capture from another thread,
+    /// since the socket itself is not `Sync`.
+    #[inline(always)]
+    pub fn pause_handle(&self) -> PauseHandle {
+        self.base().paused.clone()
+    }
+    
+    
+    /// Pin the *calling* thread to the CPU `cpu` and, as a best effort,
+    /// steer the interrupts of the queue bound to the socket to the same
+    /// CPU, so that the packets are processed where they're received
+    /// (e.g. one thread per socket, each on a distinct core).
+    ///
+    /// Steering the interrupts requires root privileges and is not
+    /// possible for every device: a warning is emitted if it fails.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the thread has been pinned to `cpu`.
+    /// * `Err(io::Error)` - If the thread couldn't be pinned (e.g. `cpu` is not a valid or allowed CPU).
+    pub fn set_cpu_affinity(&self, cpu: usize) -> io::Result<()> {
+        nethuns_pin_thread(cpu)?;
+        
+        let base = self.base();
+        let dev = base.devname.to_string_lossy();
+        let queue = base.queue.as_option();
+        match nethuns_set_irq_affinity(&dev, queue, cpu) {
+            Ok(0) => nethuns_warn!(
+                "[set_cpu_affinity] no IRQs found for {}: interrupts not steered to cpu {cpu}",
+                nethuns_dev_queue_name(Some(&dev), base.queue)
+            ),
+            Ok(_) => {}
+            Err(e) => nethuns_warn!(
+                "[set_cpu_affinity] couldn't steer the interrupts of {} to cpu {cpu}: {e}",
+                nethuns_dev_queue_name(Some(&dev), base.queue)
+            ),
+        }
+        Ok(())
+    }
+    
+    
+    /// Check if the device bound to the socket still exists,
+    /// e.g. after [`NethunsRecvError::InterfaceGone`] or
+    /// [`NethunsFlushError::InterfaceGone`], to decide whether
+    /// to stop the capture or re-open the socket later (hotplug).
+    ///
+    /// The device must still have the index it had at bind time, so
+    /// a device re-created with the same name is not considered alive.
+    /// Ports which are not kernel interfaces (e.g. VALE ports)
+    /// are always considered alive.
+    pub fn is_alive(&self) -> bool {
+        let base = self.base();
+        if base.ifindex <= 0 {
+            return true;
+        }
+        let (devname, ifindex) = (&base.devname, base.ifindex);
+        let check = || {
+            (unsafe { libc::if_nametoindex(devname.as_ptr()) }) as i32
+                == ifindex
+        };
+        match &base.opt.netns {
+            None => check(),
+            Some(netns) => nethuns_run_in_netns(netns, |entered| {
+                entered.is_ok() && check()
+            }),
+        }
+    }
+    
+    
     /// Get the file descriptor of the socket.
     #[inline(always)]
     pub fn fd(&self) -> std::os::raw::c_int {
@@ -205,6 +940,46 @@ impl NethunsSocket {
     }
     
     
+    /// Get the number of received packets waiting in the rings
+    /// of the underlying I/O framework to be returned by [`recv()`](Self::recv).
+    #[inline(always)]
+    pub fn rx_queued(&self) -> usize {
+        unsafe { (*UnsafeCell::get(&self.inner)).rx_queued() }
+    }
+    
+    
+    /// Synchronize the RX rings of the underlying I/O framework
+    /// with the kernel (e.g. `ioctl(NIOCRXSYNC)` for netmap),
+    /// making the newly received packets available to [`recv()`](Self::recv).
+    ///
+    /// [`recv()`](Self::recv) already synchronizes the rings according to
+    /// the `ring_sync_policy` option: this method lets applications which
+    /// drive their own poll loop synchronize at precise points
+    /// (e.g. after processing a batch, before sleeping).
+    ///
+    /// # Returns
+    /// * `Ok(())` - On success.
+    /// * `Err(io::Error)` - If the system call failed.
+    #[inline(always)]
+    pub fn sync_rx(&self) -> io::Result<()> {
+        unsafe { (*UnsafeCell::get(&self.inner)).sync_rx() }
+    }
+    
+    /// Synchronize the TX rings of the underlying I/O framework
+    /// with the kernel (e.g. `ioctl(NIOCTXSYNC)` for netmap),
+    /// starting the transmission of the packets pushed by [`flush()`](Self::flush).
+    ///
+    /// See [`sync_rx()`](Self::sync_rx).
+    ///
+    /// # Returns
+    /// * `Ok(())` - On success.
+    /// * `Err(io::Error)` - If the system call failed.
+    #[inline(always)]
+    pub fn sync_tx(&self) -> io::Result<()> {
+        unsafe { (*UnsafeCell::get(&self.inner)).sync_tx() }
+    }
+    
+    
     /// Get a mutable reference to the buffer inside
     /// a specific ring slot which will contain the packet
     /// to be sent.
@@ -224,6 +999,32 @@ impl NethunsSocket {
     }
     
     
+    /// Get a writer for the buffer inside a specific ring slot
+    /// which will contain the packet to be sent.
+    ///
+    /// The returned [`TxSlotWriter`] implements [`std::io::Write`]
+    /// and marks the slot as *ready for transmission* with the exact
+    /// number of written bytes when it's flushed or dropped, replacing
+    /// the manual call to [`NethunsSocket::send_slot`].
+    ///
+    /// # Arguments
+    /// * `pktid` - id of the slot.
+    ///
+    /// # Returns
+    /// * `Some(TxSlotWriter)` - writer for the slot buffer.
+    /// * `None` - if the socket is not in TX mode.
+    #[inline(always)]
+    pub fn tx_slot_writer(&mut self, pktid: usize) -> Option<TxSlotWriter> {
+        // Unique access to the socket is enforced for the whole lifetime
+        // of the writer, so the buffer can't be aliased.
+        let this: &Self = self;
+        let buffer = unsafe {
+            (*UnsafeCell::get(&this.inner)).get_packet_buffer_ref(pktid)
+        }?;
+        Some(TxSlotWriter::new(this, pktid, buffer))
+    }
+    
+    
     /// Join a fanout group.
     ///
     /// # Arguments
@@ -235,9 +1036,13 @@ impl NethunsSocket {
     }
     
     
-    /// Dump the rings of the socket.
+    /// Take a snapshot of the rings of the socket,
+    /// i.e. their indexes and the status of their slots.
+    ///
+    /// # Returns
+    /// * `Ok(RingsDump)` - The snapshot of the rings.
     #[inline(always)]
-    pub fn dump_rings(&self) {
+    pub fn dump_rings(&self) -> Result<RingsDump, NethunsDumpRingsError> {
         unsafe { (*UnsafeCell::get(&self.inner)).dump_rings() }
     }
     
@@ -248,6 +1053,176 @@ impl NethunsSocket {
         unsafe { (*UnsafeCell::get(&self.inner)).stats() }
     }
     
+    /// Check that the socket is actually bound to the queue requested
+    /// at bind time, e.g. to catch a driver which silently ignored
+    /// the queue request (useful in RSS test harnesses).
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the socket is bound to the requested queue.
+    /// * `Err(NethunsQueueBindingError::Mismatch)` - If the socket is bound to different rings. The error names the requested queue and the actual rings.
+    #[inline(always)]
+    pub fn verify_queue_binding(&self) -> Result<(), NethunsQueueBindingError> {
+        unsafe { (*UnsafeCell::get(&self.inner)).verify_queue_binding() }
+    }
+    
+    /// Get the timings of the hot paths (`recv`, filter, `send`, `flush`
+    /// and ring synchronization) of the sockets used by the current thread,
+    /// to find out where the time goes.
+    ///
+    /// Requires the `profiling` feature. Without it, the instrumentation
+    /// is compiled out, so it has no cost.
+    #[cfg(feature = "profiling")]
+    pub fn timing_report() -> TimingReport {
+        profiling::report()
+    }
+    
+    /// Reset the timings of the sockets used by the current thread
+    /// (see [`NethunsSocket::timing_report`]).
+    #[cfg(feature = "profiling")]
+    pub fn reset_timing_report() {
+        profiling::reset()
+    }
+    
+    /// Get the I/O framework used by the socket (see [`active_backend`](crate::active_backend)).
+    #[inline(always)]
+    pub fn backend(&self) -> Backend {
+        crate::active_backend()
+    }
+    
+    /// Get the features supported by the I/O framework of the socket,
+    /// so that the application can adapt at runtime instead of
+    /// calling an unsupported method.
+    #[inline(always)]
+    pub fn capabilities(&self) -> Capabilities {
+        unsafe { (*UnsafeCell::get(&self.inner)).capabilities() }
+    }
+    
+    
+    /// Check if the RX ring of the socket is frozen (stuck),
+    /// i.e. its head hasn't advanced since the previous call
+    /// despite new packets waiting in the underlying I/O framework.
+    ///
+    /// This usually means that the application is not releasing
+    /// the received packets, so that no more slots are available.
+    /// The first call only records the current state and returns `false`.
+    /// The detected freezes are counted in [`NethunsStat::freeze`].
+    ///
+    /// # Returns
+    /// `true` if the ring is frozen, `false` otherwise
+    /// or if the socket is not in RX mode.
+    pub fn is_frozen(&self) -> bool {
+        let inner = unsafe { &mut *UnsafeCell::get(&self.inner) };
+        let pending = inner.rx_pending();
+        let base = inner.base_mut();
+        
+        let head = match &base.rx_ring {
+            Some(r) => r.head(),
+            None => return false,
+        };
+        let frozen = matches!(
+            base.freeze_check,
+            Some((last_head, _)) if last_head == head && pending
+        );
+        if frozen {
+            nethuns_debug!("[is_frozen] RX ring frozen at head {head}");
+            // Count each freeze once, however many checks detect it
+            if !base.frozen {
+                base.freezes += 1;
+            }
+        } else {
+            base.freeze_check = Some((head, Instant::now()));
+        }
+        base.frozen = frozen;
+        frozen
+    }
+    
+    /// Get the time of the last freeze check in which
+    /// the RX ring of the socket was found not frozen
+    /// (see [`NethunsSocket::is_frozen`]).
+    ///
+    /// If the ring is frozen, this is (approximately) the time
+    /// since which the ring is stuck.
+    #[inline(always)]
+    pub fn last_unfrozen(&self) -> Option<Instant> {
+        self.base().freeze_check.map(|(_, t)| t)
+    }
+    
+    
+    /// Touch all the packet buffers of the socket, so that their memory
+    /// pages are faulted in before the capture loop starts.
+    ///
+    /// It avoids the latency spikes caused by page faults when the first
+    /// packets are received/sent. It can be safely called multiple times:
+    /// once the pages are mapped, it only reads one byte per buffer.
+    #[inline(always)]
+    pub fn prewarm(&self) {
+        unsafe { (*UnsafeCell::get(&self.inner)).prewarm() }
+    }
+    
+    
+    /// Get the number of received packets per L4 protocol.
+    ///
+    /// The histogram is updated only if the `classify` option
+    /// has been set when opening the socket.
+    #[inline(always)]
+    pub fn proto_histogram(&self) -> ProtoHistogram {
+        self.base().proto_histogram
+    }
+    
+    /// Reset the counters of the L4 protocol histogram,
+    /// so that rates can be computed per interval.
+    #[inline(always)]
+    pub fn reset_proto_histogram(&self) {
+        unsafe { (*UnsafeCell::get(&self.inner)).base_mut() }
+            .proto_histogram = ProtoHistogram::default();
+    }
+    
+    
+    /// Get the number of times the slots of the rings have been reused
+    /// since the socket was opened or since the last call to
+    /// [`reset_slot_turnover`](Self::reset_slot_turnover).
+    ///
+    /// It's derived from the head indexes of the rings,
+    /// so it doesn't add any cost to `recv`/`send`.
+    pub fn slot_turnover(&self) -> TurnoverStats {
+        let base = self.base();
+        let (rx_start, tx_start) = base.turnover_heads;
+        let cycles = |ring: Option<&NethunsRing>, start: usize| {
+            ring.map_or((0, 0), |r| {
+                (r.head().wrapping_sub(start) as u64, r.size())
+            })
+        };
+        let (rx_cycles, rx_size) = cycles(base.rx_ring.as_ref(), rx_start);
+        let (tx_cycles, tx_size) = cycles(base.tx_ring.as_ref(), tx_start);
+        TurnoverStats::new(rx_cycles, tx_cycles, rx_size, tx_size)
+    }
+    
+    /// Reset the slot turnover statistics,
+    /// so that rates can be computed per interval.
+    pub fn reset_slot_turnover(&self) {
+        let base = unsafe { (*UnsafeCell::get(&self.inner)).base_mut() };
+        base.turnover_heads = (
+            base.rx_ring.as_ref().map_or(0, |r| r.head()),
+            base.tx_ring.as_ref().map_or(0, |r| r.head()),
+        );
+    }
+    
+    
+    /// Get the number of times the head of the RX ring has wrapped around
+    /// the end of the ring since the socket was bound, e.g. to check,
+    /// along with [`slot_turnover`](Self::slot_turnover), whether
+    /// the application is keeping up with the traffic.
+    ///
+    /// Like the slot turnover, it's derived from the head index of
+    /// the ring, so it doesn't add any cost to `recv`.
+    ///
+    /// # Returns
+    /// * `Some(u64)` - The number of wrap-arounds of the RX ring.
+    /// * `None` - If the socket is not in RX mode.
+    pub fn rx_wrap_count(&self) -> Option<u64> {
+        self.base().rx_ring.as_ref().map(NethunsRing::wrap_count)
+    }
+    
     
     /// Check if the socket is in TX mode
     #[inline(always)]
@@ -273,3 +1248,85 @@ impl NethunsSocket {
         self.base().tx_ring.as_ref().map(|r| r.size())
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use crate::types::NethunsSocketMode;
+    
+    use super::*;
+    
+    #[test]
+    #[ignore = "requires the netmap kernel module"]
+    fn test_recv_paused() {
+        let opt = NethunsSocketOptions {
+            numblocks: 1,
+            numpackets: 64,
+            packetsize: 2048,
+            mode: NethunsSocketMode::RxOnly,
+            ..Default::default()
+        };
+        let socket = BindableNethunsSocket::open(opt)
+            .unwrap()
+            .bind("vale0:nethuns_paused", NethunsQueue::Any)
+            .unwrap();
+        
+        socket.pause();
+        assert!(matches!(socket.recv(), Err(NethunsRecvError::Paused)));
+        
+        // The wait sleeps until the deadline, instead of returning at once
+        let start = Instant::now();
+        let result = socket.recv_timeout(Duration::from_millis(50));
+        assert!(matches!(result, Err(NethunsRecvError::Paused)));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        
+        socket.resume();
+        assert!(matches!(
+            socket.recv(),
+            Err(NethunsRecvError::NoPacketsAvailable)
+        ));
+    }
+    
+    #[test]
+    #[ignore = "requires the netmap kernel module"]
+    fn test_recv_owned_header() {
+        let opt = NethunsSocketOptions {
+            numblocks: 1,
+            numpackets: 64,
+            packetsize: 2048,
+            mode: NethunsSocketMode::RxOnly,
+            ..Default::default()
+        };
+        let rx = BindableNethunsSocket::open(opt.clone())
+            .unwrap()
+            .bind("vale0:nethuns_owned_rx", NethunsQueue::Any)
+            .unwrap();
+        let tx = BindableNethunsSocket::open(NethunsSocketOptions {
+            mode: NethunsSocketMode::TxOnly,
+            ..opt
+        })
+        .unwrap()
+        .bind("vale0:nethuns_owned_tx", NethunsQueue::Any)
+        .unwrap();
+        
+        // Packets of distinct lengths, to tell their headers apart
+        for len in [60, 100, 140] {
+            tx.send(&vec![0xff; len]).unwrap();
+        }
+        tx.flush().unwrap();
+        
+        for len in [60, 100, 140] {
+            let deadline = Instant::now() + Duration::from_secs(1);
+            let packet = loop {
+                match rx.recv_owned() {
+                    Err(NethunsRecvError::NoPacketsAvailable)
+                        if Instant::now() < deadline => {}
+                    r => break r.unwrap(),
+                }
+            };
+            assert_eq!(packet.buffer().len(), len);
+            assert_eq!(packet.pkthdr().len(), len as u32);
+            assert_eq!(packet.pkthdr().snaplen(), len as u32);
+        }
+    }
+}
@@ -10,14 +10,21 @@
 
 use std::ffi::CStr;
 use std::fmt::Debug;
+use std::io;
+use std::os::unix::io::RawFd;
 
-use crate::types::{NethunsQueue, NethunsSocketOptions, NethunsStat};
+use crate::types::{
+    Capabilities, NethunsQueue, NethunsSocketOptions, NethunsStat, NetmapFlags,
+    PacketDirection,
+};
 
-use super::base::{NethunsSocketBase, RecvPacket};
+use super::base::{NethunsSocketBase, PeekedPacket, RecvPacket};
 use super::errors::{
-    NethunsBindError, NethunsFlushError, NethunsOpenError, NethunsRecvError,
-    NethunsSendError,
+    NethunsBindError, NethunsBpfError, NethunsDumpRingsError,
+    NethunsFlushError, NethunsOpenError, NethunsQueueBindingError,
+    NethunsRecvError, NethunsSendError,
 };
+use super::ring::RingsDump;
 
 
 cfg_if::cfg_if! {
@@ -70,15 +77,21 @@ pub(super) trait BindableNethunsSocketInnerTrait: Debug + Send {
     
     /// Bind an opened socket to a specific queue / any queue of interface/device `dev`.
     ///
+    /// # Arguments
+    /// * `dev`: The name of the device.
+    /// * `queue`: The queue of the device.
+    /// * `fd`: An already opened file descriptor of the I/O framework to be used for the binding, or `None` to open a new one.
+    ///
     /// # Returns
     /// * `Ok(())` - If the binding was successful.
     /// * `Err(NethunsBindError::IllegalArgument)` - If the device name contains an interior null character.
-    /// * `Err(NethunsBindError::FrameworkError)` - If an error from the unsafe interaction with underlying I/O framework occurs.
+    /// * `Err(NethunsBindError::FrameworkError)` - If an error from the unsafe interaction with underlying I/O framework occurs (e.g. `fd` is not a valid descriptor).
     /// * `Err(NethunsBindError::Error)` - If an unexpected error occurs.
     fn bind(
         self: Box<Self>,
         dev: &str,
         queue: NethunsQueue,
+        fd: Option<RawFd>,
     ) -> Result<Box<NethunsSocketInner>, (NethunsBindError, Box<Self>)>
     where
         Self: Sized;
@@ -116,20 +129,37 @@ pub(super) trait NethunsSocketInnerTrait: Debug + Send {
     /// * `Err(NethunsRecvError::NotRx)` -  If the socket is not configured in RX mode. Check the configuration parameters passed to [`BindableNethunsSocket::open`](super::BindableNethunsSocket::open).
     /// * `Err(NethunsRecvError::InUse)` - If the slot at the head of the RX ring is currently in use, i.e. the corresponding received packet is not released yet.
     /// * `Err(NethunsRecvError::NoPacketsAvailable)` - If there are no new packets available in the RX ring.
-    /// * `Err(NethunsRecvError::PacketFiltered)` - If the packet is filtered out by the `filter` function specified during socket configuration.
+    /// * `Err(NethunsRecvError::PacketFiltered)` - If the packet is filtered out by the `filter` function specified during socket configuration, or suppressed as a duplicate of the previous one (see the `dedup` option).
+    /// * `Err(NethunsRecvError::FilterPanicked)` - If the `filter` function panicked.
     /// * `Err(NethunsRecvError::FrameworkError)` - If an error from the unsafe interaction with underlying I/O framework occurs.
+    /// * `Err(NethunsRecvError::InterfaceGone)` - If the device bound to the socket doesn't exist anymore (see [`NethunsSocket::is_alive`](crate::sockets::NethunsSocket::is_alive)).
     /// * `Err(NethunsRecvError::Error)` - If an unexpected error occurs.
     fn recv(&mut self) -> Result<RecvPacket, NethunsRecvError>;
     
     
+    /// Inspect the next unprocessed received packet without consuming it.
+    ///
+    /// The packet is not checked against the `filter` function.
+    ///
+    /// # Returns
+    /// * `Ok(PeekedPacket)` - The next unprocessed received packet, if no error occurred.
+    /// * `Err(NethunsRecvError::NotRx)` -  If the socket is not configured in RX mode.
+    /// * `Err(NethunsRecvError::InUse)` - If the slot at the head of the RX ring is currently in use.
+    /// * `Err(NethunsRecvError::NoPacketsAvailable)` - If there are no new packets available, or no free buffers to receive them.
+    /// * `Err(NethunsRecvError::FrameworkError)` - If an error from the unsafe interaction with underlying I/O framework occurs.
+    /// * `Err(NethunsRecvError::InterfaceGone)` - If the device bound to the socket doesn't exist anymore (see [`NethunsSocket::is_alive`](crate::sockets::NethunsSocket::is_alive)).
+    /// * `Err(NethunsRecvError::Error)` - If an unexpected error occurs.
+    fn recv_peek(&mut self) -> Result<PeekedPacket, NethunsRecvError>;
+    
+    
     /// Queue up a packet for transmission.
     ///
     /// # Returns
-    /// * `Ok(())` - On success.
+    /// * `Ok(usize)` - The id of the TX ring slot which contains the packet, on success.
     /// * `Err(NethunsSendError::NotTx)` -  If the socket is not configured in TX mode. Check the configuration parameters passed to [`BindableNethunsSocket::open`](super::BindableNethunsSocket::open).
     /// * `Err(NethunsSendError::InvalidPacketSize)` - If the packet is too large.
     /// * `Err(NethunsSendError::InUse)` - If the slot at the tail of the TX ring is not released yet and it's currently in use by the application.
-    fn send(&mut self, packet: &[u8]) -> Result<(), NethunsSendError>;
+    fn send(&mut self, packet: &[u8]) -> Result<usize, NethunsSendError>;
     
     
     /// Send all queued up packets.
@@ -138,6 +168,7 @@ pub(super) trait NethunsSocketInnerTrait: Debug + Send {
     /// * `Ok(())` - On success.
     /// * `Err(NethunsFlushError::NotTx)` -  If the socket is not configured in TX mode. Check the configuration parameters passed to [`BindableNethunsSocket::open`](super::BindableNethunsSocket::open).
     /// * `Err(NethunsFlushError::FrameworkError)` - If an error from the unsafe interaction with underlying I/O framework occurs.
+    /// * `Err(NethunsFlushError::InterfaceGone)` - If the device bound to the socket doesn't exist anymore (see [`NethunsSocket::is_alive`](crate::sockets::NethunsSocket::is_alive)).
     /// * `Err(NethunsFlushError::Error)` - If an unexpected error occurs.
     fn flush(&mut self) -> Result<(), NethunsFlushError>;
     
@@ -163,6 +194,32 @@ pub(super) trait NethunsSocketInnerTrait: Debug + Send {
     fn fd(&self) -> std::os::raw::c_int;
     
     
+    /// Check if there are received packets pending in the rings
+    /// of the underlying I/O framework, which haven't been
+    /// moved to the RX ring of the socket yet.
+    fn rx_pending(&self) -> bool;
+    
+    /// Get the number of received packets queued in the rings
+    /// of the underlying I/O framework, which haven't been
+    /// moved to the RX ring of the socket yet.
+    fn rx_queued(&self) -> usize;
+    
+    
+    /// Synchronize the RX rings of the underlying I/O framework
+    /// with the kernel, making the newly received packets available.
+    fn sync_rx(&self) -> io::Result<()>;
+    
+    /// Synchronize the TX rings of the underlying I/O framework
+    /// with the kernel, starting the transmission of the flushed packets
+    /// and reclaiming the slots of the completed ones.
+    fn sync_tx(&self) -> io::Result<()>;
+    
+    
+    /// Touch all the packet buffers of the socket, so that their
+    /// memory pages are faulted in before the first packet arrives.
+    fn prewarm(&self);
+    
+    
     /// Get a mutable reference to the buffer inside
     /// a specific ring slot which will contain the packet
     /// to be sent.
@@ -186,13 +243,48 @@ pub(super) trait NethunsSocketInnerTrait: Debug + Send {
     fn fanout(&mut self, group: i32, fanout: &CStr) -> bool;
     
     
-    /// Dump the rings of the socket.
-    fn dump_rings(&mut self);
+    /// Take a snapshot of the rings of the socket.
+    ///
+    /// # Returns
+    /// * `Ok(RingsDump)` - The snapshot of the rings.
+    fn dump_rings(&mut self) -> Result<RingsDump, NethunsDumpRingsError>;
     
     
     /// Get some statistics about the socket
     /// or `None` on error.
     fn stats(&self) -> Option<NethunsStat>;
+    
+    
+    /// Get the features supported by the I/O framework of the socket.
+    fn capabilities(&self) -> Capabilities;
+    
+    
+    /// Attach a BPF program to the socket, so that the packets
+    /// are filtered in-kernel. The program has already been checked
+    /// to be a non-empty sequence of 8-byte instructions.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the program was attached.
+    /// * `Err(NethunsBpfError::NotSupported)` - If the I/O framework doesn't support BPF filters.
+    /// * `Err(NethunsBpfError::Error)` - If the kernel rejected the program (e.g. verifier error).
+    fn attach_bpf(&mut self, prog: &[u8]) -> Result<(), NethunsBpfError>;
+    
+    /// Detach the BPF program attached to the socket, if any.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If no program is attached anymore.
+    /// * `Err(NethunsBpfError::NotSupported)` - If the I/O framework doesn't support BPF filters.
+    /// * `Err(NethunsBpfError::Error)` - If the system call failed.
+    fn detach_bpf(&mut self) -> Result<(), NethunsBpfError>;
+    
+    
+    /// Check that the rings actually bound to the socket
+    /// match the requested queue.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the socket is bound to the requested queue.
+    /// * `Err(NethunsQueueBindingError::Mismatch)` - If the socket is bound to different rings.
+    fn verify_queue_binding(&self) -> Result<(), NethunsQueueBindingError>;
 }
 
 
@@ -220,4 +312,23 @@ pub trait PkthdrTrait: Debug + Send + Sync {
     
     fn offvlan_tpid(&self) -> u16;
     fn offvlan_tci(&self) -> u16;
+    
+    /// Index of the hardware queue which delivered the packet,
+    /// or `None` if it's not known.
+    fn queue_id(&self) -> Option<u16>;
+    
+    /// Number of packets lost immediately before this one,
+    /// or 0 if the underlying I/O framework doesn't report it.
+    fn gap(&self) -> u32;
+    
+    /// Flags of the netmap slot which contained the packet,
+    /// or an empty set if the packet wasn't received through netmap.
+    fn netmap_flags(&self) -> NetmapFlags;
+    
+    /// Index of the netmap buffer which contains the packet,
+    /// or `None` if the packet isn't stored in a netmap buffer.
+    fn netmap_buf_idx(&self) -> Option<u32>;
+    
+    /// Direction of the packet (received or transmitted by the local host).
+    fn direction(&self) -> PacketDirection;
 }
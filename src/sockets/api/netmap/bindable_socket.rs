@@ -2,23 +2,32 @@
 //! for the netmap framework.
 
 use std::ffi::CString;
+use std::os::unix::io::RawFd;
 use std::ptr::NonNull;
-use std::{thread, time};
+use std::{io, thread, time};
 
-use c_netmap_wrapper::macros::{netmap_buf, netmap_rxring};
+use c_netmap_wrapper::macros::netmap_rxring;
 use c_netmap_wrapper::{NetmapRing, NmPortDescriptor};
 
+use crate::logging::nethuns_warn;
 use crate::misc::circular_queue::CircularQueue;
-use crate::misc::{nethuns_dev_queue_name, nethuns_set_if_promisc};
+use crate::misc::{
+    nethuns_clear_if_up, nethuns_dev_queue_name, nethuns_if_num_rx_queues,
+    nethuns_memlock_limit, nethuns_set_if_promisc, nethuns_set_if_up,
+};
 use crate::sockets::api::{
     BindableNethunsSocketInnerTrait, NethunsSocketInner,
 };
 use crate::sockets::base::NethunsSocketBase;
 use crate::sockets::errors::{NethunsBindError, NethunsOpenError};
 use crate::sockets::ring::NethunsRing;
-use crate::types::{NethunsQueue, NethunsSocketMode, NethunsSocketOptions};
+use crate::types::{
+    NethunsCaptureDir, NethunsQueue, NethunsRingSyncPolicy, NethunsSocketMode,
+    NethunsSocketOptions,
+};
 
 use super::nethuns_socket::NethunsSocketNetmap;
+use super::utility::{extra_bufs_needed, NetmapBufferPool};
 
 
 /// [`BindableNethunsSocket`](crate::sockets::BindableNethunsSocket) inner implementation
@@ -42,18 +51,59 @@ impl BindableNethunsSocketInnerTrait for BindableNethunsSocketNetmap {
             ));
         }
         
+        if opt.ring_sync_policy == NethunsRingSyncPolicy::SyncEveryN(0) {
+            return Err(NethunsOpenError::InvalidOptions(
+                "ring_sync_policy: SyncEveryN requires N > 0".to_owned(),
+            ));
+        }
+        
+        if opt.rx_ring_size == Some(0) || opt.tx_ring_size == Some(0) {
+            return Err(NethunsOpenError::InvalidOptions(
+                "rx_ring_size and tx_ring_size must be greater than 0"
+                    .to_owned(),
+            ));
+        }
+        
+        if opt.max_tx_batch == Some(0) {
+            return Err(NethunsOpenError::InvalidOptions(
+                "max_tx_batch must be greater than 0".to_owned(),
+            ));
+        }
+        
+        // netmap always bypasses the qdisc layer, so `tx_qdisc_bypass`
+        // is ignored. Warn only if it can't have any effect at all.
+        if opt.tx_qdisc_bypass && !tx {
+            nethuns_warn!(
+                "[open] tx_qdisc_bypass has no effect on a socket not in TX mode"
+            );
+        }
+        
+        // Check that the rings fit in the memory which can be locked
+        if let Some(limit) = nethuns_memlock_limit() {
+            let required = opt.estimated_memory();
+            if required as u64 > limit {
+                let msg = format!(
+                    "the rings require about {required} bytes of memory, but RLIMIT_MEMLOCK is {limit} bytes (check the ring sizes and packetsize)"
+                );
+                if opt.strict_memlock {
+                    return Err(NethunsOpenError::InvalidOptions(msg));
+                }
+                nethuns_warn!("[open] {msg}");
+            }
+        }
+        
         let mut base = NethunsSocketBase::default();
         
         if rx {
             base.rx_ring = Some(NethunsRing::new(
-                (opt.numblocks * opt.numpackets) as _,
+                opt.rx_ring_slots(),
                 opt.packetsize as _,
             ));
         }
         
         if tx {
             base.tx_ring = Some(NethunsRing::new(
-                (opt.numblocks * opt.numpackets) as _,
+                opt.tx_ring_slots(),
                 opt.packetsize as _,
             ));
         }
@@ -69,6 +119,7 @@ impl BindableNethunsSocketInnerTrait for BindableNethunsSocketNetmap {
         mut self: Box<Self>,
         dev: &str,
         queue: NethunsQueue,
+        fd: Option<RawFd>,
     ) -> Result<Box<NethunsSocketInner>, (NethunsBindError, Box<Self>)> {
         // Prepare flag and prefix for device name
         let flags = if !self.tx() {
@@ -91,13 +142,41 @@ impl BindableNethunsSocketInnerTrait for BindableNethunsSocketNetmap {
             "-".to_owned() // FIXME: I'm not sure about this, but it's taken directly from the original code
         };
         
+        // Select the RX rings according to the capture direction,
+        // if the host rings are requested: the packets transmitted
+        // by the host stack are captured from the host rings
+        // ("^" host rings only, "*" both).
+        let dir = self.base.opt.dir;
+        let host_rings = match queue {
+            _ if !self.rx()
+                || !self.base.opt.host_rings
+                || dev.starts_with("vale") =>
+            {
+                ""
+            }
+            NethunsQueue::Some(_) => {
+                if dir != NethunsCaptureDir::In {
+                    nethuns_warn!(
+                        "[bind] dev {}: the host rings require binding to any queue",
+                        nethuns_dev_queue_name(Some(dev), queue)
+                    );
+                }
+                ""
+            }
+            NethunsQueue::Any => match dir {
+                NethunsCaptureDir::In => "",
+                NethunsCaptureDir::Out => "^",
+                NethunsCaptureDir::InOut => "*",
+            },
+        };
+        
         // Build the device name
         let nm_dev = match CString::new(match queue {
             NethunsQueue::Some(idx) => {
                 format!("{prefix}{dev}{connector}{idx}{flags}")
             }
             NethunsQueue::Any => {
-                format!("{prefix}{dev}{flags}")
+                format!("{prefix}{dev}{host_rings}{flags}")
             }
         }) {
             Ok(nm_dev) => nm_dev,
@@ -141,17 +220,50 @@ impl BindableNethunsSocketInnerTrait for BindableNethunsSocketNetmap {
         
         
         // Configure the Netmap port descriptor
-        // with the number of required extra buffers
-        let rx_ring_size =
-            self.base.rx_ring.as_ref().map(|r| r.size()).unwrap_or(0) as u32;
-        let tx_ring_size =
-            self.base.tx_ring.as_ref().map(|r| r.size()).unwrap_or(0) as u32;
-        let extra_bufs = (if self.tx() { rx_ring_size } else { 0_u32 })
-            + (if self.rx() { tx_ring_size } else { 0_u32 });
+        // with the number of required extra buffers.
+        // When binding to any queue, the socket receives packets
+        // from all the RX rings of the device, thus the extra buffers
+        // for reception must be provisioned for each of them.
+        let rx_ring_size = self.base.rx_ring.as_ref().map(|r| r.size() as u32);
+        let tx_ring_size = self.base.tx_ring.as_ref().map(|r| r.size() as u32);
+        let num_rx_queues = match queue {
+            NethunsQueue::Any => nethuns_if_num_rx_queues(dev).unwrap_or(1),
+            NethunsQueue::Some(_) => 1,
+        };
+        let num_rx_queues = match host_rings {
+            "^" => 1,
+            "*" => num_rx_queues + 1,
+            _ => num_rx_queues,
+        };
+        let extra_bufs = extra_bufs_needed(
+            rx_ring_size,
+            tx_ring_size,
+            num_rx_queues,
+            self.base.opt.extra_buffers,
+        );
+        let extra_bufs = match extra_bufs {
+            Some(n) => n,
+            None => {
+                return Err((
+                    NethunsBindError::IllegalArgument(format!(
+                        "dev {}: the number of extra bufs overflows ({} queues, `extra_buffers` = {})",
+                        nethuns_dev_queue_name(Some(dev), queue),
+                        num_rx_queues,
+                        self.base.opt.extra_buffers,
+                    )),
+                    self,
+                ))
+            }
+        };
         nm_port_d.reg.nr_extra_bufs = extra_bufs;
         
-        // Open the initialized netmap port descriptor
-        if let Err(e) = nm_port_d.open_desc() {
+        // Open the initialized netmap port descriptor,
+        // on the provided file descriptor if any
+        let opened = match fd {
+            None => nm_port_d.open_desc(),
+            Some(fd) => nm_port_d.open_desc_with_fd(fd),
+        };
+        if let Err(e) = opened {
             return Err((
                 NethunsBindError::FrameworkError(format!(
                     "NmPortDescriptor.open_desc(): couldn't open dev {} ({})",
@@ -164,12 +276,20 @@ impl BindableNethunsSocketInnerTrait for BindableNethunsSocketNetmap {
         
         // Check if the number of extra buffers is correct
         if nm_port_d.reg.nr_extra_bufs != extra_bufs {
+            nethuns_warn!(
+                "[bind] dev {}: extra buffers shortfall ({} requested, {} obtained)",
+                nethuns_dev_queue_name(Some(dev), queue),
+                extra_bufs,
+                nm_port_d.reg.nr_extra_bufs,
+            );
+            release_caller_fd(&mut nm_port_d, fd);
             return Err((
                 NethunsBindError::FrameworkError(format!(
-                    "dev {}: cannot obtain {} extra bufs (got {})",
+                    "dev {}: cannot obtain {} extra bufs, including {} from the `extra_buffers` option (got {})",
                     nethuns_dev_queue_name(Some(dev), queue),
                     extra_bufs,
-                    nm_port_d.reg.nr_extra_bufs
+                    self.base.opt.extra_buffers,
+                    nm_port_d.reg.nr_extra_bufs,
                 )),
                 self,
             ));
@@ -191,6 +311,7 @@ impl BindableNethunsSocketInnerTrait for BindableNethunsSocketNetmap {
             match NonNull::new(ptr) {
                 Some(ptr) => ptr,
                 None => {
+                    release_caller_fd(&mut nm_port_d, fd);
                     return Err((
                         NethunsBindError::FrameworkError(
                             "failed to initialize some_ring: netmap_rxring returned null"
@@ -207,39 +328,54 @@ impl BindableNethunsSocketInnerTrait for BindableNethunsSocketNetmap {
         let mut free_ring =
             CircularQueue::new(nm_port_d.reg.nr_extra_bufs as _, &|| 0);
         
-        // Retrieve the ring slots generated by the kernel
-        let mut scan = unsafe { (*nm_port_d.nifp).ni_bufs_head };
+        // Retrieve the extra buffers allocated by the kernel
+        // [SAFETY] `nifp` is valid, since the port has been opened,
+        // and `some_ring` belongs to the same port
+        let mut pool =
+            unsafe { NetmapBufferPool::new(nm_port_d.nifp, &some_ring) };
         // Case 1: TX
         if let Some(tx_ring) = &mut self.base.tx_ring {
             for i in 0..tx_ring.size() {
-                tx_ring.get_slot_mut(i).pkthdr.buf_idx = scan;
-                scan = unsafe {
-                    let ptr = netmap_buf(&some_ring, scan as _) as *const u32;
-                    debug_assert!(!ptr.is_null());
-                    ptr.read_unaligned()
-                }
+                // The number of extra buffers has been checked above
+                let idx = pool.alloc();
+                debug_assert!(idx.is_some());
+                tx_ring.get_slot_mut(i).pkthdr.buf_idx = idx.unwrap_or(0);
             }
         }
         // Case 2: RX
         if self.rx() {
-            while scan != 0 {
-                unsafe { free_ring.push_unchecked(scan) };
-                scan = unsafe {
-                    let ptr = netmap_buf(&some_ring, scan as _) as *const u32;
-                    debug_assert!(!ptr.is_null());
-                    ptr.read_unaligned()
-                };
+            while let Some(idx) = pool.alloc() {
+                unsafe { free_ring.push_unchecked(idx) };
             }
         }
-        // Reset the index of the first of the extra buffers
-        unsafe {
-            (*nm_port_d.nifp).ni_bufs_head = 0;
-        }
+        // Detach the remaining extra buffers, if any
+        pool.clear();
+        
         
+        if self.base.opt.interface_up {
+            // Bring the interface up, if needed
+            match nethuns_set_if_up(&c_dev) {
+                Ok(changed) => self.base.if_brought_up = changed,
+                Err(e) => {
+                    let msg = if e.kind() == io::ErrorKind::PermissionDenied {
+                        format!("couldn't bring dev {dev} up: the `interface_up` option requires CAP_NET_ADMIN ({e})")
+                    } else {
+                        format!("couldn't bring dev {dev} up: {e}")
+                    };
+                    release_caller_fd(&mut nm_port_d, fd);
+                    return Err((NethunsBindError::Error(msg), self));
+                }
+            }
+        }
         
         if self.base.opt.promisc {
             // Set the interface in promisc mode
             if let Err(e) = nethuns_set_if_promisc(&c_dev) {
+                if self.base.if_brought_up {
+                    let _ = nethuns_clear_if_up(&c_dev);
+                    self.base.if_brought_up = false;
+                }
+                release_caller_fd(&mut nm_port_d, fd);
                 return Err((
                     NethunsBindError::Error(format!(
                         "couldn't set promisc mode: {e}"
@@ -272,3 +408,14 @@ impl BindableNethunsSocketInnerTrait for BindableNethunsSocketNetmap {
         &self.base
     }
 }
+
+
+/// Give the file descriptor passed to
+/// [`bind_with_fd`](crate::sockets::BindableNethunsSocket::bind_with_fd)
+/// back to the caller, so that it's not closed when `nm_port_d`
+/// is dropped on a bind failure.
+fn release_caller_fd(nm_port_d: &mut NmPortDescriptor, fd: Option<RawFd>) {
+    if fd.is_some() {
+        nm_port_d.release_fd();
+    }
+}
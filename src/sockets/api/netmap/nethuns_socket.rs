@@ -2,29 +2,44 @@
 //! for the netmap framework.
 
 use std::ffi::CStr;
-use std::ptr::NonNull;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::atomic::Ordering;
-use std::{cmp, mem, slice};
+use std::{cmp, io, mem, slice};
 
 use c_netmap_wrapper::bindings::{nm_pkt_copy, NS_BUF_CHANGED};
 use c_netmap_wrapper::constants::{NIOCRXSYNC, NIOCTXSYNC};
-use c_netmap_wrapper::macros::{netmap_buf, netmap_txring};
+use c_netmap_wrapper::macros::netmap_buf;
 use c_netmap_wrapper::{netmap_buf_pkt, NetmapRing, NmPortDescriptor};
 
+use crate::logging::{nethuns_error, nethuns_warn};
 use crate::misc::circular_queue::CircularQueue;
-use crate::misc::nethuns_clear_if_promisc;
+use crate::misc::{
+    nethuns_clear_if_promisc, nethuns_clear_if_up, nethuns_run_in_netns,
+};
 use crate::sockets::api::NethunsSocketInnerTrait;
-use crate::sockets::base::{NethunsSocketBase, RecvPacket};
+use crate::sockets::base::{
+    FragmentState, NethunsSocketBase, PeekedPacket, RecvPacket,
+};
 use crate::sockets::errors::{
-    NethunsFlushError, NethunsRecvError, NethunsSendError,
+    NethunsBpfError, NethunsDumpRingsError, NethunsFlushError,
+    NethunsQueueBindingError, NethunsRecvError, NethunsSendError,
 };
+use crate::sockets::flow_rule::nethuns_flow_rule_delete;
+use crate::sockets::profiling::nethuns_timed;
 use crate::sockets::ring::{
-    nethuns_ring_free_slots, NethunsRingSlot, RingSlotStatus,
+    nethuns_ring_free_slots, NethunsRingSlot, RingSlotStatus, RingsDump,
+};
+use crate::sockets::PkthdrTrait;
+use crate::types::{
+    Capabilities, NethunsRingSyncPolicy, NethunsStat, NetmapFlags,
 };
-use crate::types::NethunsStat;
 
+#[cfg(debug_assertions)]
+use super::utility::{check_rings, CHECK_RINGS_PERIOD};
 use super::utility::{
-    nethuns_blocks_free, nethuns_get_buf_addr_netmap, non_empty_rx_ring,
+    device_gone, nethuns_blocks_free, nethuns_get_buf_addr_netmap,
+    non_empty_rx_ring, nth_sync_call, prefetch, ring_direction,
+    rings_match_queue, NetmapBufferPool,
 };
 
 
@@ -56,6 +71,19 @@ pub struct NethunsSocketNetmap {
     /// in the `netmap_slot`, so that it can be given back to
     /// netmap to receive more packets.
     free_ring: CircularQueue<u32>,
+    
+    /// Number of `recv` calls since the last synchronization of the RX rings
+    /// (used by [`NethunsRingSyncPolicy::SyncEveryN`]).
+    rx_sync_calls: u32,
+    
+    /// Number of `flush` calls since the last synchronization of the TX rings
+    /// (used by [`NethunsRingSyncPolicy::SyncEveryN`]).
+    tx_sync_calls: u32,
+    
+    /// Number of `recv` and `flush` calls since the last consistency check
+    /// of the rings (debug builds only).
+    #[cfg(debug_assertions)]
+    check_rings_calls: u32,
 }
 // fields rx and tx removed because redundant with
 // base.rx_ring.is_some() and base.tx_ring.is_some()
@@ -74,6 +102,37 @@ impl NethunsSocketNetmap {
             p,
             some_ring,
             free_ring,
+            rx_sync_calls: 0,
+            tx_sync_calls: 0,
+            #[cfg(debug_assertions)]
+            check_rings_calls: 0,
+        }
+    }
+    
+    
+    /// Undo the configuration applied to the device through the socket
+    /// (promisc mode, `interface_up` option, hardware flow rules).
+    fn restore_device(&mut self) {
+        // Clear promisc mode of interface if previously set
+        if self.base.opt.promisc {
+            if let Err(e) = nethuns_clear_if_promisc(&self.base.devname) {
+                nethuns_error!("[NethunsSocketNetmap::Drop] couldn't clear promisc mode: {e}");
+            }
+        }
+        
+        // Bring the interface down if previously brought up
+        if self.base.if_brought_up {
+            if let Err(e) = nethuns_clear_if_up(&self.base.devname) {
+                nethuns_error!("[NethunsSocketNetmap::Drop] couldn't restore the interface state: {e}");
+            }
+        }
+        
+        // Remove the hardware flow rules installed through the socket
+        for handle in self.base.flow_rules.drain(..) {
+            if let Err(e) = nethuns_flow_rule_delete(&self.base.devname, handle)
+            {
+                nethuns_error!("[NethunsSocketNetmap::Drop] couldn't remove flow rule {}: {e}", handle.location());
+            }
         }
     }
 }
@@ -92,6 +151,7 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
         if rx_ring.get_slot(head_idx).status.load(Ordering::Acquire)
             != RingSlotStatus::Free
         {
+            self.base.app_dropped += 1;
             return Err(NethunsRecvError::InUse);
         }
         
@@ -115,16 +175,43 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
             );
         }
         
+        // Synchronize the rx rings in advance, if required by the policy
+        let sync_policy = self.base.opt.ring_sync_policy;
+        let sync_now = match sync_policy {
+            NethunsRingSyncPolicy::SyncEveryCall => true,
+            NethunsRingSyncPolicy::SyncOnEmpty => false,
+            NethunsRingSyncPolicy::SyncEveryN(n) => {
+                nth_sync_call(&mut self.rx_sync_calls, n)
+            }
+        };
+        if sync_now
+            && nethuns_timed!(Sync, unsafe {
+                libc::ioctl(self.p.fd, NIOCRXSYNC)
+            }) < 0
+            && device_gone()
+        {
+            return Err(NethunsRecvError::InterfaceGone);
+        }
+        
         // Find the first non-empty netmap ring.
         let mut netmap_ring = match non_empty_rx_ring(&mut self.p) {
             Ok(r) => r,
-            Err(_) => {
+            Err(e) => {
+                if sync_policy != NethunsRingSyncPolicy::SyncOnEmpty {
+                    return Err(e);
+                }
                 // All netmap rings are empty.
                 // Try again after synchronizing the rx rings
                 // of the socket.
                 // If it still fails, return an error
                 // (no packets available at the moment).
-                unsafe { libc::ioctl(self.p.fd, NIOCRXSYNC) };
+                if nethuns_timed!(Sync, unsafe {
+                    libc::ioctl(self.p.fd, NIOCRXSYNC)
+                }) < 0
+                    && device_gone()
+                {
+                    return Err(NethunsRecvError::InterfaceGone);
+                }
                 non_empty_rx_ring(&mut self.p)?
             }
         };
@@ -137,6 +224,8 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
             .map_err(NethunsRecvError::Error)?;
         let idx = cur_netmap_slot.buf_idx;
         let pkt = unsafe { netmap_buf_pkt!(netmap_ring, idx) };
+        // Received bytes of the packet (`pkt` spans the whole buffer)
+        let pkt_len = cur_netmap_slot.len as usize;
         
         // Update the packet header metadata of the nethuns ring abstraction
         // against the actual netmap packet.
@@ -146,6 +235,9 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
             slot.pkthdr.caplen = cur_netmap_slot.len as _;
             slot.pkthdr.len = cur_netmap_slot.len as _;
             slot.pkthdr.buf_idx = idx;
+            slot.pkthdr.queue_id = Some(self.p.cur_rx_ring);
+            slot.pkthdr.flags = cur_netmap_slot.flags;
+            slot.pkthdr.direction = ring_direction(&self.p);
         }
         
         // Assign a new buffer to the netmap `cur` slot and set the relative flag
@@ -156,27 +248,163 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
         netmap_ring.cur = unsafe { netmap_ring.nm_ring_next(i) };
         netmap_ring.head = unsafe { netmap_ring.nm_ring_next(i) };
         
-        // Filter the packet
-        if match &self.base.filter {
-            None => false,
-            Some(filter) => {
-                // Call the filter closure
-                !filter(&rx_ring.get_slot(head_idx).pkthdr, pkt)
+        // Prefetch the buffer of the next packet, if already received
+        if self.base.opt.prefetch && netmap_ring.cur != netmap_ring.tail {
+            if let Ok(next) = netmap_ring.get_slot(netmap_ring.cur as _) {
+                let buf =
+                    unsafe { netmap_buf(&netmap_ring, next.buf_idx as _) };
+                prefetch(buf as *const u8);
+            }
+        }
+        
+        // Let the tap observe the packet, before it can be filtered out.
+        // Its panics are caught for the same reason of the filter ones,
+        // and the tap is removed so that it can't flood the log.
+        if let Some(tap) = &self.base.tap {
+            let pkt = &pkt[..pkt_len];
+            if panic::catch_unwind(AssertUnwindSafe(|| tap(pkt))).is_err() {
+                nethuns_warn!("[recv] the tap panicked: removing it");
+                self.base.tap = None;
+            }
+        }
+        
+        // Track the packets fragmented across multiple slots:
+        // the following fragments share the fate of the first one.
+        let more_frag = NetmapFlags::from_bits_truncate(
+            rx_ring.get_slot(head_idx).pkthdr.flags,
+        )
+        .contains(NetmapFlags::MOREFRAG);
+        let fragment = mem::replace(
+            &mut self.base.fragment,
+            if more_frag {
+                FragmentState::Continuation
+            } else {
+                FragmentState::First
+            },
+        );
+        if fragment == FragmentState::Discard {
+            if more_frag {
+                self.base.fragment = FragmentState::Discard;
             }
-        } {
             nethuns_ring_free_slots!(self, rx_ring, nethuns_blocks_free);
+            // [SAFETY] a buffer has just been popped from the free ring.
+            unsafe { self.free_ring.push_unchecked(idx) };
             return Err(NethunsRecvError::PacketFiltered);
         }
         
+        // Filter the packet (only the first fragment, if fragmented).
+        // Without a filter, the whole block (including the read of
+        // the packet header) is skipped.
+        // A panic of the filter closure is caught, so that it doesn't
+        // unwind through the rings while the slot is half-processed.
+        if let (Some(filter), FragmentState::First) =
+            (&self.base.filter, fragment)
+        {
+            let pkthdr = &rx_ring.get_slot(head_idx).pkthdr;
+            let hash = if self.base.opt.rxhash {
+                // netmap doesn't expose the RSS hash computed by the NIC
+                Some(pkthdr.rxhash()).filter(|&h| h != 0)
+            } else {
+                None
+            };
+            match nethuns_timed!(
+                Filter,
+                panic::catch_unwind(AssertUnwindSafe(|| {
+                    filter(pkthdr, pkt, hash)
+                }))
+            ) {
+                Ok(true) => {}
+                Ok(false) => {
+                    nethuns_ring_free_slots!(
+                        self,
+                        rx_ring,
+                        nethuns_blocks_free
+                    );
+                    // Give the buffer of the dropped packet back to the
+                    // free ring, since the head slot isn't advanced.
+                    // [SAFETY] a buffer has just been popped from it.
+                    unsafe { self.free_ring.push_unchecked(idx) };
+                    if more_frag {
+                        self.base.fragment = FragmentState::Discard;
+                    }
+                    self.base.filtered += 1;
+                    return Err(NethunsRecvError::PacketFiltered);
+                }
+                Err(_) => {
+                    nethuns_ring_free_slots!(
+                        self,
+                        rx_ring,
+                        nethuns_blocks_free
+                    );
+                    // [SAFETY] a buffer has just been popped from it.
+                    unsafe { self.free_ring.push_unchecked(idx) };
+                    if more_frag {
+                        self.base.fragment = FragmentState::Discard;
+                    }
+                    if self.base.opt.disable_filter_on_panic {
+                        nethuns_warn!(
+                            "[recv] the filter panicked: disabling it"
+                        );
+                        self.base.filter = None;
+                    }
+                    return Err(NethunsRecvError::FilterPanicked);
+                }
+            }
+        }
+        
+        // Suppress the duplicates of the previous packet, if required
+        if fragment == FragmentState::First
+            && self.base.opt.dedup
+            && self.base.dedup.is_duplicate(&pkt[..pkt_len])
+        {
+            nethuns_ring_free_slots!(self, rx_ring, nethuns_blocks_free);
+            // [SAFETY] a buffer has just been popped from the free ring.
+            unsafe { self.free_ring.push_unchecked(idx) };
+            if more_frag {
+                self.base.fragment = FragmentState::Discard;
+            }
+            self.base.filtered += 1;
+            return Err(NethunsRecvError::PacketFiltered);
+        }
+        
+        if self.base.opt.classify {
+            self.base.proto_histogram.count(&pkt[..pkt_len]);
+        }
+        
         {
             let slot = rx_ring.get_slot_mut(head_idx);
-            slot.pkthdr.caplen =
-                cmp::min(self.base.opt.packetsize, slot.pkthdr.caplen);
+            slot.pkthdr.caplen = cmp::min(
+                self.base.opt.effective_snaplen(),
+                slot.pkthdr.caplen,
+            );
             slot.status.store(RingSlotStatus::InUse, Ordering::Release);
         }
         
         rx_ring.rings_mut().advance_head();
         
+        #[cfg(debug_assertions)]
+        if nth_sync_call(&mut self.check_rings_calls, CHECK_RINGS_PERIOD) {
+            if let Err(e) = check_rings(
+                Some(&*rx_ring),
+                self.base.tx_ring.as_ref(),
+                self.free_ring.tail().wrapping_sub(self.free_ring.head()),
+                self.p.reg.nr_extra_bufs as _,
+            ) {
+                panic!("[recv] inconsistent rings: {e}");
+            }
+        }
+        
+        // Zero the stale bytes of the buffer beyond `caplen`, if required.
+        // The packet is then accessed through a new slice, so that
+        // no reference to the whole buffer is alive during the write.
+        let caplen = rx_ring.get_slot(head_idx).pkthdr.caplen as usize;
+        let buf_size = pkt.len();
+        let buf = unsafe { netmap_buf(&netmap_ring, idx as _) } as *mut u8;
+        if self.base.opt.zero_slots {
+            unsafe { buf.add(caplen).write_bytes(0, buf_size - caplen) };
+        }
+        let pkt = unsafe { slice::from_raw_parts_mut(buf, caplen) };
+        
         let recv_packet = {
             // IMPORTANT!! slot MUST be an **immutable** reference,
             // otherwise the Rust memory model rules will be broken.
@@ -194,7 +422,97 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
     }
     
     
-    fn send(&mut self, packet: &[u8]) -> Result<(), NethunsSendError> {
+    fn recv_peek(&mut self) -> Result<PeekedPacket, NethunsRecvError> {
+        let rx_ring = match &mut self.base.rx_ring {
+            Some(r) => r,
+            None => return Err(NethunsRecvError::NotRx),
+        };
+        
+        let head_idx = rx_ring.head();
+        if rx_ring.get_slot(head_idx).status.load(Ordering::Acquire)
+            != RingSlotStatus::Free
+        {
+            return Err(NethunsRecvError::InUse);
+        }
+        
+        // Fail as `recv` would if no free buffers are available,
+        // so that a successful peek is followed by a successful `recv`
+        if self.free_ring.is_empty() {
+            nethuns_ring_free_slots!(self, rx_ring, nethuns_blocks_free);
+            
+            if self.free_ring.is_empty() {
+                return Err(NethunsRecvError::NoPacketsAvailable);
+            }
+        }
+        
+        // Synchronize the rx rings in advance, if required by the policy
+        let sync_policy = self.base.opt.ring_sync_policy;
+        let sync_now = match sync_policy {
+            NethunsRingSyncPolicy::SyncEveryCall => true,
+            NethunsRingSyncPolicy::SyncOnEmpty => false,
+            NethunsRingSyncPolicy::SyncEveryN(n) => {
+                nth_sync_call(&mut self.rx_sync_calls, n)
+            }
+        };
+        if sync_now
+            && nethuns_timed!(Sync, unsafe {
+                libc::ioctl(self.p.fd, NIOCRXSYNC)
+            }) < 0
+            && device_gone()
+        {
+            return Err(NethunsRecvError::InterfaceGone);
+        }
+        
+        // Find the first non-empty netmap ring,
+        // after synchronizing the rx rings if needed.
+        let netmap_ring = match non_empty_rx_ring(&mut self.p) {
+            Ok(r) => r,
+            Err(e) => {
+                if sync_policy != NethunsRingSyncPolicy::SyncOnEmpty {
+                    return Err(e);
+                }
+                if nethuns_timed!(Sync, unsafe {
+                    libc::ioctl(self.p.fd, NIOCRXSYNC)
+                }) < 0
+                    && device_gone()
+                {
+                    return Err(NethunsRecvError::InterfaceGone);
+                }
+                non_empty_rx_ring(&mut self.p)?
+            }
+        };
+        
+        // Read the packet in the `cur` netmap ring slot,
+        // without moving the `cur` and `head` indexes.
+        let cur_netmap_slot = netmap_ring
+            .get_slot(netmap_ring.cur as _)
+            .map_err(NethunsRecvError::Error)?;
+        let idx = cur_netmap_slot.buf_idx;
+        let pkt = unsafe { netmap_buf_pkt!(netmap_ring, idx) };
+        
+        // The head slot is free, so its packet header can be safely
+        // overwritten: it will be filled again by the next `recv`.
+        let slot = rx_ring.get_slot_mut(head_idx);
+        slot.pkthdr.ts = netmap_ring.ts;
+        slot.pkthdr.len = cur_netmap_slot.len as _;
+        slot.pkthdr.caplen = cmp::min(
+            self.base.opt.effective_snaplen(),
+            cur_netmap_slot.len as _,
+        );
+        slot.pkthdr.buf_idx = idx;
+        slot.pkthdr.queue_id = Some(self.p.cur_rx_ring);
+        slot.pkthdr.flags = cur_netmap_slot.flags;
+        slot.pkthdr.direction = ring_direction(&self.p);
+        
+        let slot = rx_ring.get_slot(head_idx);
+        Ok(PeekedPacket::new(
+            &slot.pkthdr,
+            &pkt[..slot.pkthdr.caplen as _],
+        ))
+    }
+    
+    
+    fn send(&mut self, packet: &[u8]) -> Result<usize, NethunsSendError> {
         let tx_ring = match &mut self.base.tx_ring {
             Some(r) => r,
             None => return Err(NethunsSendError::NotTx),
@@ -228,10 +546,11 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
                 packet.len() as _,
             )
         };
-        tx_ring.nethuns_send_slot(tx_ring.tail(), packet.len());
+        let id = tx_ring.tail() % tx_ring.size();
+        tx_ring.nethuns_send_slot(id, packet.len());
         tx_ring.rings_mut().advance_tail();
         
-        Ok(())
+        Ok(id)
     }
     
     
@@ -246,25 +565,25 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
                 .into_boxed_slice();
         
         let mut head = tx_ring.head();
+        let mut budget = self.base.opt.max_tx_batch.unwrap_or(usize::MAX);
         
-        // Try to push packets marked for transmission
-        for i in (self.p.first_tx_ring as _)..=(self.p.last_tx_ring as _) {
-            let mut ring = NetmapRing::new(
-                NonNull::new(
-                    unsafe { netmap_txring(self.p.nifp, i) }
-                )
-                .ok_or(
-                    NethunsFlushError::FrameworkError(
-                        "failed to initialize some_ring: netmap_txring returned null".to_owned()
-                    )
-                )?
-            );
-            prev_tails[i - self.p.first_tx_ring as usize] = ring.tail;
+        // Try to push packets marked for transmission,
+        // at most `max_tx_batch` of them
+        for (i, ring_idx) in
+            (self.p.first_tx_ring..=self.p.last_tx_ring).enumerate()
+        {
+            let mut ring = self.p.tx_ring(ring_idx).ok_or_else(|| {
+                NethunsFlushError::FrameworkError(format!(
+                    "netmap_txring returned null for ring {ring_idx}"
+                ))
+            })?;
+            prev_tails[i] = ring.tail;
             
             loop {
                 let slot = tx_ring.get_slot_mut(head);
                 
-                if ring.nm_ring_empty()
+                if budget == 0
+                    || ring.nm_ring_empty()
                     || slot.status.load(Ordering::Acquire)
                         != RingSlotStatus::InUse
                 {
@@ -293,11 +612,27 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
                 ring.cur = unsafe { ring.nm_ring_next(ring.head) };
                 ring.head = ring.cur;
                 head += 1;
+                budget -= 1;
                 tx_ring.rings_mut().advance_head();
             }
         }
         
-        if unsafe { libc::ioctl(self.p.fd, NIOCTXSYNC) < 0 } {
+        let sync_now = match self.base.opt.ring_sync_policy {
+            NethunsRingSyncPolicy::SyncEveryCall
+            | NethunsRingSyncPolicy::SyncOnEmpty => true,
+            NethunsRingSyncPolicy::SyncEveryN(n) => {
+                nth_sync_call(&mut self.tx_sync_calls, n)
+            }
+        };
+        
+        if sync_now
+            && nethuns_timed!(Sync, unsafe {
+                libc::ioctl(self.p.fd, NIOCTXSYNC)
+            }) < 0
+        {
+            if device_gone() {
+                return Err(NethunsFlushError::InterfaceGone);
+            }
             return Err(NethunsFlushError::Error(format!(
                 "ioctl({:?}, {:?}) failed with errno {}",
                 self.p.fd,
@@ -309,22 +644,16 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
         // cleanup completed transmissions: for each completed
         // netmap slot, mark the corresponding nethuns slot as
         // available (status <- Free)
-        for i in (self.p.first_tx_ring as _)..=(self.p.last_tx_ring as _) {
-            let ring = NetmapRing::new(
-                NonNull::new(
-                    unsafe { netmap_txring(self.p.nifp, i) }
-                )
-                .ok_or(
-                    NethunsFlushError::FrameworkError(
-                        "failed to initialize some_ring: netmap_txring returned null".to_owned()
-                    )
-                )?
-            );
-            
+        for (i, ring_idx) in
+            (self.p.first_tx_ring..=self.p.last_tx_ring).enumerate()
+        {
+            let ring = self.p.tx_ring(ring_idx).ok_or_else(|| {
+                NethunsFlushError::FrameworkError(format!(
+                    "netmap_txring returned null for ring {ring_idx}"
+                ))
+            })?;
             let stop = unsafe { ring.nm_ring_next(ring.tail) };
-            let mut scan = unsafe {
-                ring.nm_ring_next(prev_tails[i - self.p.first_tx_ring as usize])
-            };
+            let mut scan = unsafe { ring.nm_ring_next(prev_tails[i]) };
             
             while scan != stop {
                 let mut netmap_slot = ring
@@ -339,6 +668,18 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
             }
         }
         
+        #[cfg(debug_assertions)]
+        if nth_sync_call(&mut self.check_rings_calls, CHECK_RINGS_PERIOD) {
+            if let Err(e) = check_rings(
+                self.base.rx_ring.as_ref(),
+                self.base.tx_ring.as_ref(),
+                self.free_ring.tail().wrapping_sub(self.free_ring.head()),
+                self.p.reg.nr_extra_bufs as _,
+            ) {
+                panic!("[flush] inconsistent rings: {e}");
+            }
+        }
+        
         Ok(())
     }
     
@@ -378,6 +719,71 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
     }
     
     
+    fn rx_pending(&self) -> bool {
+        self.p.rx_rings().any(|ring| ring.cur != ring.tail)
+    }
+    
+    
+    fn rx_queued(&self) -> usize {
+        self.p
+            .rx_rings()
+            .map(|ring| {
+                ((ring.tail + ring.num_slots - ring.cur) % ring.num_slots)
+                    as usize
+            })
+            .sum()
+    }
+    
+    
+    fn sync_rx(&self) -> io::Result<()> {
+        if unsafe { libc::ioctl(self.p.fd, NIOCRXSYNC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    
+    fn sync_tx(&self) -> io::Result<()> {
+        if unsafe { libc::ioctl(self.p.fd, NIOCTXSYNC) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    
+    
+    fn prewarm(&self) {
+        // Read one byte from the buffer with index `idx`
+        let touch = |ring: &NetmapRing, idx: u32| {
+            let buf = unsafe { netmap_buf(ring, idx as _) } as *const u8;
+            debug_assert!(!buf.is_null());
+            unsafe { buf.read_volatile() };
+        };
+        
+        // Buffers currently attached to the netmap RX and TX rings
+        for ring in self.p.rx_rings().chain(self.p.tx_rings()) {
+            for i in 0..ring.num_slots {
+                if let Ok(slot) = ring.get_slot(i as _) {
+                    touch(&ring, slot.buf_idx);
+                }
+            }
+        }
+        
+        // Buffers owned by the rings of the socket
+        for ring in [&self.base.rx_ring, &self.base.tx_ring]
+            .into_iter()
+            .flatten()
+        {
+            for i in 0..ring.size() {
+                touch(&self.some_ring, ring.get_slot(i).pkthdr.buf_idx);
+            }
+        }
+        
+        // Extra buffers not assigned to any ring yet
+        for i in self.free_ring.head()..self.free_ring.tail() {
+            touch(&self.some_ring, self.free_ring.clone_get(i));
+        }
+    }
+    
+    
     #[inline(always)]
     fn get_packet_buffer_ref(&self, pktid: usize) -> Option<&mut [u8]> {
         self.base.tx_ring.as_ref().map(|tx_ring| unsafe {
@@ -392,49 +798,164 @@ impl NethunsSocketInnerTrait for NethunsSocketNetmap {
         false
     }
     
-    /// NOT IMPLEMENTED IN NETMAP
     #[inline(always)]
-    fn dump_rings(&mut self) {}
+    fn dump_rings(&mut self) -> Result<RingsDump, NethunsDumpRingsError> {
+        Ok(RingsDump {
+            rx: self.base.rx_ring.as_ref().map(|r| r.dump()),
+            tx: self.base.tx_ring.as_ref().map(|r| r.dump()),
+        })
+    }
     
     #[inline(always)]
     fn stats(&self) -> Option<NethunsStat> {
-        Some(NethunsStat::default())
+        Some(NethunsStat {
+            freeze: self.base.freezes,
+            app_dropped: self.base.app_dropped,
+            filtered: self.base.filtered,
+            deduped: self.base.dedup.deduped,
+            ..Default::default()
+        })
+    }
+    
+    #[inline(always)]
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            // Not implemented in netmap
+            fanout: false,
+            // The payload is copied from the RX to the TX buffers
+            zero_copy_forward: false,
+            // The ring timestamp is taken by the kernel at each rxsync
+            hw_timestamps: false,
+            stats: true,
+            flow_rules: true,
+            // netmap bypasses the kernel network stack
+            bpf_filters: false,
+        }
+    }
+    
+    fn attach_bpf(&mut self, _prog: &[u8]) -> Result<(), NethunsBpfError> {
+        Err(NethunsBpfError::NotSupported(
+            "netmap bypasses the kernel network stack: use a filter closure or a hardware flow rule"
+                .to_owned(),
+        ))
+    }
+    
+    fn detach_bpf(&mut self) -> Result<(), NethunsBpfError> {
+        Err(NethunsBpfError::NotSupported(
+            "netmap bypasses the kernel network stack".to_owned(),
+        ))
+    }
+    
+    fn verify_queue_binding(&self) -> Result<(), NethunsQueueBindingError> {
+        // Rings of the direction used by the socket
+        let (first, last, num) = if self.base.rx_ring.is_some() {
+            (
+                self.p.first_rx_ring,
+                self.p.last_rx_ring,
+                self.p.reg.nr_rx_rings,
+            )
+        } else {
+            (
+                self.p.first_tx_ring,
+                self.p.last_tx_ring,
+                self.p.reg.nr_tx_rings,
+            )
+        };
+        let (first, last, num) =
+            (u32::from(first), u32::from(last), u32::from(num));
+        
+        if !rings_match_queue(
+            self.base.queue,
+            self.p.reg.nr_mode,
+            (first, last),
+            num,
+        ) {
+            return Err(NethunsQueueBindingError::Mismatch(
+                self.base.queue.to_string(),
+                format!("rings {first}..={last} (out of {num})"),
+            ));
+        }
+        Ok(())
     }
 }
 
 
 impl Drop for NethunsSocketNetmap {
     fn drop(&mut self) {
-        // Clear promisc mode of interface if previously set
-        if self.base.opt.promisc {
-            if let Err(e) = nethuns_clear_if_promisc(&self.base.devname) {
-                eprintln!("[NethunsSocketNetmap::Drop] couldn't clear promisc mode: {e}");
+        // Give the packets queued up for transmission a last chance
+        // to be sent, without panicking inside `drop`
+        let queued = self.base.tx_ring.as_ref().is_some_and(|ring| {
+            (0..ring.size()).any(|i| {
+                ring.get_slot(i).status.load(Ordering::Acquire)
+                    == RingSlotStatus::InUse
+            })
+        });
+        if queued {
+            // Push all the queued packets, not just one batch
+            self.base.opt.max_tx_batch = None;
+            match panic::catch_unwind(AssertUnwindSafe(|| self.flush())) {
+                Ok(Ok(())) => {
+                    // Synchronize the TX rings whatever the sync policy
+                    unsafe { libc::ioctl(self.p.fd, NIOCTXSYNC) };
+                }
+                Ok(Err(e)) => {
+                    nethuns_warn!("[NethunsSocketNetmap::Drop] couldn't flush the queued packets: {e}");
+                }
+                Err(_) => {
+                    nethuns_warn!("[NethunsSocketNetmap::Drop] couldn't flush the queued packets: flush panicked");
+                }
             }
         }
         
+        // Restore the configuration of the device,
+        // from inside its network namespace if needed
+        match self.base.opt.netns.clone() {
+            None => self.restore_device(),
+            Some(netns) => nethuns_run_in_netns(&netns, |entered| {
+                match entered {
+                    Ok(()) => self.restore_device(),
+                    Err(e) => {
+                        nethuns_error!("[NethunsSocketNetmap::Drop] couldn't restore the device configuration: {e}");
+                    }
+                }
+            }),
+        }
+        
+        // Give the extra buffers back to the kernel
+        // [SAFETY] `nifp` is valid until `p` is dropped,
+        // and `some_ring` belongs to the same port
+        let mut pool =
+            unsafe { NetmapBufferPool::new(self.p.nifp, &self.some_ring) };
+        let mut returned = 0_u32;
+        let mut return_buf = |idx: u32| {
+            pool.free(idx);
+            returned += 1;
+        };
+        
         if let Some(ring) = &self.base.tx_ring {
             for i in 0..ring.size() {
-                let idx = ring.get_slot(i).pkthdr.buf_idx;
-                let next = unsafe {
-                    netmap_buf(&self.some_ring, idx as _) as *mut u32
-                };
-                debug_assert!(!next.is_null());
-                unsafe {
-                    *next = (*self.p.nifp).ni_bufs_head;
-                    (*self.p.nifp).ni_bufs_head = idx;
-                };
+                return_buf(ring.get_slot(i).pkthdr.buf_idx);
+            }
+        }
+        
+        // Buffers of the packets received but not yet released
+        if let Some(ring) = &self.base.rx_ring {
+            for i in ring.tail()..ring.head() {
+                return_buf(ring.get_slot(i).pkthdr.buf_idx);
             }
         }
         
         while !self.free_ring.is_empty() {
-            let idx = self.free_ring.clone_pop_unchecked();
-            let next =
-                unsafe { netmap_buf(&self.some_ring, idx as _) as *mut u32 };
-            debug_assert!(!next.is_null());
-            unsafe {
-                *next = (*self.p.nifp).ni_bufs_head;
-                (*self.p.nifp).ni_bufs_head = idx;
-            };
+            return_buf(self.free_ring.clone_pop_unchecked());
+        }
+        
+        // Check that no buffer has been lost (debug builds only)
+        if cfg!(debug_assertions) && returned != self.p.reg.nr_extra_bufs {
+            nethuns_warn!(
+                "[NethunsSocketNetmap::Drop] buffer leak detected: {} extra buffers obtained, {} returned",
+                self.p.reg.nr_extra_bufs,
+                returned,
+            );
         }
     }
 }
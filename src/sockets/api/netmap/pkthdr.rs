@@ -3,6 +3,7 @@
 use c_netmap_wrapper::bindings::timeval;
 
 use crate::sockets::PkthdrTrait;
+use crate::types::{NetmapFlags, PacketDirection};
 
 
 /// Packet header containing metadata
@@ -12,6 +13,12 @@ pub struct PkthdrNetmap {
     pub len: u32,
     pub caplen: u32,
     pub buf_idx: u32,
+    /// Index of the netmap RX ring the packet came from
+    pub queue_id: Option<u16>,
+    /// Flags of the netmap slot the packet was received in
+    pub flags: u16,
+    /// `Out` if the packet came from a host ring
+    pub direction: PacketDirection,
 }
 
 
@@ -68,4 +75,27 @@ impl PkthdrTrait for PkthdrNetmap {
     fn offvlan_tci(&self) -> u16 {
         0
     }
+    #[inline(always)]
+    fn queue_id(&self) -> Option<u16> {
+        self.queue_id
+    }
+    #[inline(always)]
+    fn gap(&self) -> u32 {
+        // netmap doesn't report per-packet losses
+        0
+    }
+    #[inline(always)]
+    fn netmap_flags(&self) -> NetmapFlags {
+        NetmapFlags::from_bits_truncate(self.flags)
+    }
+    #[inline(always)]
+    fn netmap_buf_idx(&self) -> Option<u32> {
+        // netmap buffers 0 and 1 are reserved, so 0 means that
+        // the packet isn't stored in a netmap buffer (e.g. pcap files)
+        (self.buf_idx != 0).then_some(self.buf_idx)
+    }
+    #[inline(always)]
+    fn direction(&self) -> PacketDirection {
+        self.direction
+    }
 }
@@ -2,10 +2,16 @@
 
 use std::ptr::NonNull;
 
-use c_netmap_wrapper::macros::netmap_rxring;
+use c_netmap_wrapper::bindings::{
+    netmap_if, NR_REG_NIC_SW, NR_REG_ONE_NIC, NR_REG_SW,
+};
+use c_netmap_wrapper::macros::{netmap_buf, netmap_rxring};
 use c_netmap_wrapper::{NetmapRing, NmPortDescriptor};
 
 use crate::sockets::errors::NethunsRecvError;
+#[cfg(debug_assertions)]
+use crate::sockets::ring::{NethunsRing, RingKind};
+use crate::types::{NethunsQueue, PacketDirection};
 
 
 /// Finds the first non-empty RX ring within the given Netmap port descriptor.
@@ -69,6 +75,239 @@ pub(super) fn non_empty_rx_ring(
 }
 
 
+/// Get the direction of the packets received from the current RX ring
+/// of the netmap port descriptor `d`.
+///
+/// The host rings follow the hardware rings, so packets coming from them
+/// have been transmitted by the host stack.
+#[inline(always)]
+pub(super) fn ring_direction(d: &NmPortDescriptor) -> PacketDirection {
+    if u32::from(d.cur_rx_ring) >= u32::from(d.reg.nr_rx_rings) {
+        PacketDirection::Out
+    } else {
+        PacketDirection::In
+    }
+}
+
+
+/// Check if the N-th call since the last synchronization of a ring
+/// has been reached, according to the [`NethunsRingSyncPolicy::SyncEveryN`](crate::types::NethunsRingSyncPolicy::SyncEveryN) policy.
+///
+/// # Arguments
+/// * `calls` - counter of the calls since the last synchronization. It's reset when the N-th call is reached.
+/// * `n` - number of calls between two synchronizations.
+///
+/// # Returns
+/// `true` if the ring must be synchronized, `false` otherwise.
+pub(super) fn nth_sync_call(calls: &mut u32, n: u32) -> bool {
+    *calls += 1;
+    if *calls >= n {
+        *calls = 0;
+        true
+    } else {
+        false
+    }
+}
+
+
+/// Hint the CPU to load the cache line which contains `ptr`
+/// (no-op on the architectures other than x86_64).
+#[inline(always)]
+pub(super) fn prefetch(ptr: *const u8) {
+    #[cfg(target_arch = "x86_64")]
+    // [SAFETY] ok: prefetching is only a hint, which never faults
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch(ptr as *const i8, _MM_HINT_T0);
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = ptr;
+}
+
+
+/// Check if the rings `first..=last` of a netmap port registered
+/// in mode `mode` (`NR_REG_*`) are the ones expected for a socket
+/// bound to `queue`, on a device with `num` hardware rings.
+///
+/// When bound to any queue, the host rings follow the hardware rings
+/// and are included by the `"^"` (host rings only, `NR_REG_SW`)
+/// and `"*"` (both, `NR_REG_NIC_SW`) port specs.
+pub(super) fn rings_match_queue(
+    queue: NethunsQueue,
+    mode: u32,
+    (first, last): (u32, u32),
+    num: u32,
+) -> bool {
+    match queue {
+        NethunsQueue::Some(idx) => {
+            mode == NR_REG_ONE_NIC as u32 && first == idx && last == idx
+        }
+        NethunsQueue::Any if mode == NR_REG_SW as u32 => {
+            first >= num && last >= first
+        }
+        NethunsQueue::Any if mode == NR_REG_NIC_SW as u32 => {
+            first == 0 && last >= num
+        }
+        // All the hardware rings, possibly followed by the host ring
+        NethunsQueue::Any => first == 0 && last + 1 >= num,
+    }
+}
+
+
+/// Compute the number of netmap extra buffers needed by a socket.
+///
+/// Each slot of the nethuns RX ring holds the buffer of a received
+/// packet, for each RX queue the socket receives from, plus the
+/// `extra_buffers` requested through the options; each slot of the
+/// nethuns TX ring owns a buffer as well.
+///
+/// # Arguments
+/// * `rx_ring_size` - size of the RX ring, `None` if not in RX mode.
+/// * `tx_ring_size` - size of the TX ring, `None` if not in TX mode.
+/// * `num_rx_queues` - number of RX queues the socket receives from.
+/// * `extra_buffers` - additional buffers for reception.
+///
+/// # Returns
+/// * `Some(n)` - The number of extra buffers.
+/// * `None` - If the number overflows.
+pub(super) fn extra_bufs_needed(
+    rx_ring_size: Option<u32>,
+    tx_ring_size: Option<u32>,
+    num_rx_queues: u32,
+    extra_buffers: u32,
+) -> Option<u32> {
+    let rx_bufs = match rx_ring_size {
+        Some(size) => size
+            .checked_mul(num_rx_queues)?
+            .checked_add(extra_buffers)?,
+        None => 0,
+    };
+    rx_bufs.checked_add(tx_ring_size.unwrap_or(0))
+}
+
+
+/// Check if the last system call on the netmap port failed because
+/// the bound device doesn't exist anymore (e.g. unplugged USB NIC,
+/// deleted veth), rather than for a transient reason.
+#[inline(always)]
+pub(super) fn device_gone() -> bool {
+    matches!(errno::errno().0, libc::ENODEV | libc::ENXIO)
+}
+
+
+/// Free list of the netmap extra buffers, rooted in the
+/// `ni_bufs_head` field of the netmap interface.
+///
+/// The list is intrusive: the first 4 bytes of each free buffer
+/// contain the index of the next one, and the index 0 terminates it.
+#[derive(Debug)]
+pub(super) struct NetmapBufferPool<'a> {
+    nifp: *mut netmap_if,
+    some_ring: &'a NetmapRing,
+}
+
+impl<'a> NetmapBufferPool<'a> {
+    /// Create a view over the free list of the netmap interface `nifp`.
+    ///
+    /// # Safety
+    /// `nifp` must point to a valid `netmap_if` object, and `some_ring`
+    /// must be a ring of the same netmap port, so that the buffer indices
+    /// of the list are valid for it.
+    pub(super) unsafe fn new(
+        nifp: *mut netmap_if,
+        some_ring: &'a NetmapRing,
+    ) -> Self {
+        debug_assert!(!nifp.is_null());
+        Self { nifp, some_ring }
+    }
+    
+    /// Take a buffer from the head of the free list.
+    ///
+    /// # Returns
+    /// * `Some(u32)` - The index of the buffer.
+    /// * `None` - If the list is empty.
+    pub(super) fn alloc(&mut self) -> Option<u32> {
+        // [SAFETY] `nifp` is valid (see `new`)
+        let idx = unsafe { (*self.nifp).ni_bufs_head };
+        if idx == 0 {
+            return None;
+        }
+        // [SAFETY] `idx` is a buffer of the list, so it's valid for `some_ring`
+        let next = unsafe {
+            let ptr = netmap_buf(self.some_ring, idx as _) as *const u32;
+            debug_assert!(!ptr.is_null());
+            ptr.read_unaligned()
+        };
+        unsafe { (*self.nifp).ni_bufs_head = next };
+        Some(idx)
+    }
+    
+    /// Put the buffer `idx` at the head of the free list.
+    pub(super) fn free(&mut self, idx: u32) {
+        // [SAFETY] `nifp` is valid and `idx` is a buffer of the port
+        unsafe {
+            let ptr = netmap_buf(self.some_ring, idx as _) as *mut u32;
+            debug_assert!(!ptr.is_null());
+            ptr.write_unaligned((*self.nifp).ni_bufs_head);
+            (*self.nifp).ni_bufs_head = idx;
+        }
+    }
+    
+    /// Detach the remaining buffers from the free list,
+    /// which becomes empty.
+    pub(super) fn clear(&mut self) {
+        unsafe { (*self.nifp).ni_bufs_head = 0 };
+    }
+}
+
+
+/// Number of `recv` and `flush` calls between two consistency checks
+/// of the rings, which cost a scan of the RX ring (debug builds only).
+#[cfg(debug_assertions)]
+pub(super) const CHECK_RINGS_PERIOD: u32 = 1024;
+
+
+/// Check the consistency of the rings of a socket and the accounting
+/// of the netmap extra buffers (debug builds only).
+///
+/// Each extra buffer must be either attached to a TX slot, held by
+/// a received packet not released yet, or available in the free ring.
+///
+/// # Arguments
+/// * `rx_ring` - the RX ring of the socket, if any
+/// * `tx_ring` - the TX ring of the socket, if any
+/// * `free_bufs` - number of buffers in the free ring
+/// * `extra_bufs` - number of extra buffers obtained from netmap
+///
+/// # Returns
+/// * `Ok(())` - If the rings are consistent.
+/// * `Err(String)` - The description of the first violated invariant.
+#[cfg(debug_assertions)]
+pub(super) fn check_rings(
+    rx_ring: Option<&NethunsRing>,
+    tx_ring: Option<&NethunsRing>,
+    free_bufs: usize,
+    extra_bufs: usize,
+) -> Result<(), String> {
+    let mut held_bufs = free_bufs;
+    if let Some(ring) = rx_ring {
+        ring.check_invariants(RingKind::Rx)?;
+        held_bufs += ring.head().wrapping_sub(ring.tail());
+    }
+    if let Some(ring) = tx_ring {
+        ring.check_invariants(RingKind::Tx)?;
+        held_bufs += ring.size();
+    }
+    
+    if held_bufs != extra_bufs {
+        return Err(format!(
+            "{held_bufs} extra buffers are accounted for, but {extra_bufs} have been obtained"
+        ));
+    }
+    Ok(())
+}
+
+
 /// Add the id of a newly available ring slot
 /// to the list of currently available slots.
 ///
@@ -110,3 +349,50 @@ macro_rules! nethuns_get_buf_addr_netmap {
     };
 }
 pub(super) use nethuns_get_buf_addr_netmap;
+
+
+#[cfg(test)]
+mod tests {
+    use c_netmap_wrapper::bindings::NR_REG_ALL_NIC;
+    
+    use super::*;
+    
+    #[test]
+    fn test_rings_match_queue() {
+        let (one, all) = (NR_REG_ONE_NIC as u32, NR_REG_ALL_NIC as u32);
+        let (sw, nic_sw) = (NR_REG_SW as u32, NR_REG_NIC_SW as u32);
+        
+        // Single queue, out of 4 hardware rings
+        assert!(rings_match_queue(NethunsQueue::Some(2), one, (2, 2), 4));
+        assert!(!rings_match_queue(NethunsQueue::Some(2), one, (0, 3), 4));
+        assert!(!rings_match_queue(NethunsQueue::Some(2), all, (2, 2), 4));
+        
+        // Any queue: hardware rings only
+        assert!(rings_match_queue(NethunsQueue::Any, all, (0, 3), 4));
+        assert!(!rings_match_queue(NethunsQueue::Any, all, (1, 3), 4));
+        
+        // Any queue: host rings only ("^")
+        assert!(rings_match_queue(NethunsQueue::Any, sw, (4, 4), 4));
+        assert!(!rings_match_queue(NethunsQueue::Any, sw, (0, 4), 4));
+        
+        // Any queue: hardware and host rings ("*")
+        assert!(rings_match_queue(NethunsQueue::Any, nic_sw, (0, 4), 4));
+        assert!(!rings_match_queue(NethunsQueue::Any, nic_sw, (0, 3), 4));
+    }
+    
+    #[test]
+    fn test_extra_bufs_needed() {
+        // RX only: one buffer per slot and queue, plus the extra ones
+        assert_eq!(extra_bufs_needed(Some(4096), None, 1, 0), Some(4096));
+        assert_eq!(extra_bufs_needed(Some(256), None, 4, 16), Some(1040));
+        
+        // TX only: one buffer per slot, regardless of the options
+        assert_eq!(extra_bufs_needed(None, Some(512), 4, 16), Some(512));
+        
+        // RX and TX, with rings of different sizes
+        assert_eq!(extra_bufs_needed(Some(256), Some(64), 2, 8), Some(584));
+        
+        assert_eq!(extra_bufs_needed(Some(u32::MAX), None, 2, 0), None);
+        assert_eq!(extra_bufs_needed(Some(1), Some(u32::MAX), 1, 0), None);
+    }
+}
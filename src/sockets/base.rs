@@ -1,15 +1,30 @@
 //! Common structures for all the implementation of a Nethuns socket.
 
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
 use std::ffi::CString;
 use std::fmt::{self, Debug, Display};
-use std::sync::atomic;
+use std::hash::Hasher;
+use std::ops::Deref;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use std::{cmp, io, thread};
 
 use derivative::Derivative;
 
-use crate::types::{NethunsFilter, NethunsQueue, NethunsSocketOptions};
+use crate::logging::nethuns_error;
+use crate::types::{
+    NethunsFilterWithHash, NethunsQueue, NethunsSocketOptions, NethunsTap,
+    NetmapFlags, PacketDirection, ProtoHistogram,
+};
 
+use super::api::Pkthdr;
+use super::errors::NethunsSendError;
+use super::flow_rule::RuleHandle;
+use super::pcap::{nethuns_pcap_pkthdr, nethuns_pcap_record};
 use super::ring::{AtomicRingSlotStatus, NethunsRing, RingSlotStatus};
-use super::PkthdrTrait;
+use super::{NethunsSocket, PkthdrTrait};
 
 
 /// Base structure for a `NethunsSocket`.
@@ -39,13 +54,165 @@ pub(crate) struct NethunsSocketBase {
     pub ifindex: i32,
     
     /// Closure used for filtering received packets.
+    /// Filters without the RSS hash are adapted to this type.
     #[derivative(Debug = "ignore")]
-    pub filter: Option<Box<NethunsFilter>>,
+    pub filter: Option<Box<NethunsFilterWithHash>>,
+    
+    /// Closure which observes every received packet before the filter.
+    #[derivative(Debug = "ignore")]
+    pub tap: Option<Box<NethunsTap>>,
+    
+    /// Number of calls to `recv` which failed with `InUse` because
+    /// the head slot of the RX ring was still in use by the application.
+    /// It isn't a number of packets: the packets stay in the ring
+    /// and are received once the slot is released.
+    pub app_dropped: u64,
+    
+    /// Number of packets discarded by the filter
+    /// or suppressed as duplicates.
+    pub filtered: u64,
+    
+    /// Number of received packets per L4 protocol
+    /// (updated only if `opt.classify` is set).
+    pub proto_histogram: ProtoHistogram,
+    
+    /// Head of the RX ring seen during the last freeze check
+    /// and time of the check.
+    pub freeze_check: Option<(usize, Instant)>,
+    
+    /// Whether the RX ring was frozen at the last freeze check.
+    pub frozen: bool,
+    
+    /// Number of freezes of the RX ring detected by the freeze checks.
+    pub freezes: u64,
+    
+    /// Hardware flow rules installed on the device through the socket,
+    /// which must be removed when the socket is dropped.
+    pub flow_rules: Vec<RuleHandle>,
+    
+    /// Whether the device has been brought up at bind time
+    /// (see the `interface_up` option), so that it must be brought
+    /// down when the socket is dropped.
+    pub if_brought_up: bool,
+    
+    /// Head indexes of the RX and TX rings at the last reset
+    /// of the slot turnover statistics.
+    pub turnover_heads: (usize, usize),
+    
+    /// Suppression of the consecutive duplicate packets
+    /// (used only if `opt.dedup` is set).
+    pub dedup: PacketDedup,
+    
+    /// Whether the capture is paused, shared with the [`PauseHandle`]s
+    /// of the socket.
+    pub paused: PauseHandle,
+    
+    /// Position of the next RX slot inside a packet fragmented
+    /// across multiple slots.
+    pub fragment: FragmentState,
 }
 // errbuf removed => use Result as return type
 // filter_ctx removed => use closures with move semantics
 
 
+/// Position of the next received slot inside a packet fragmented across
+/// multiple slots (see [`NetmapFlags::MOREFRAG`]).
+///
+/// The filter and the deduplication are applied to the first fragment
+/// only: the following ones share its fate.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum FragmentState {
+    /// The next slot starts a new packet.
+    #[default]
+    First,
+    /// The next slot continues the packet being received.
+    Continuation,
+    /// The next slot continues a discarded packet, so it must be
+    /// discarded too (up to the last fragment of the packet).
+    Discard,
+}
+
+
+/// State of the suppression of consecutive duplicate packets
+/// (see the `dedup` option).
+#[derive(Debug, Default)]
+pub(crate) struct PacketDedup {
+    /// Hash of the bytes of the last received packet.
+    last_hash: Option<u64>,
+    /// Number of packets suppressed as duplicates of the previous one.
+    pub deduped: u64,
+}
+
+impl PacketDedup {
+    /// Check if the received packet `pkt` is byte-identical to the
+    /// previous one, and remember it for the next check.
+    ///
+    /// Packets are compared through a 64-bit hash of their bytes.
+    ///
+    /// # Returns
+    /// `true` if the packet must be suppressed, `false` otherwise.
+    pub fn is_duplicate(&mut self, pkt: &[u8]) -> bool {
+        let mut hasher = DefaultHasher::new();
+        hasher.write(pkt);
+        let hash = hasher.finish();
+        
+        if self.last_hash.replace(hash) == Some(hash) {
+            self.deduped += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+
+/// Handle to pause and resume the capture of a
+/// [`NethunsSocket`](crate::sockets::NethunsSocket) from any thread
+/// (see [`NethunsSocket::pause_handle`](crate::sockets::NethunsSocket::pause_handle)).
+#[derive(Clone, Debug, Default)]
+pub struct PauseHandle(Arc<AtomicBool>);
+
+impl PauseHandle {
+    /// Pause the capture: the RX ring isn't drained anymore
+    /// until [`resume`](Self::resume) is called.
+    #[inline(always)]
+    pub fn pause(&self) {
+        self.0.store(true, atomic::Ordering::Release);
+    }
+    
+    /// Resume the capture.
+    #[inline(always)]
+    pub fn resume(&self) {
+        self.0.store(false, atomic::Ordering::Release);
+    }
+    
+    /// Check if the capture is paused.
+    #[inline(always)]
+    pub fn is_paused(&self) -> bool {
+        self.0.load(atomic::Ordering::Acquire)
+    }
+    
+    /// Wait until the capture is resumed, sleeping at most until `deadline`.
+    ///
+    /// # Returns
+    /// `true` if the capture is not paused anymore,
+    /// `false` if it's still paused at the deadline.
+    pub(crate) fn wait_resumed(&self, deadline: Instant) -> bool {
+        // Interval between two checks of the flag
+        const POLL_INTERVAL: Duration = Duration::from_millis(1);
+        
+        while self.is_paused() {
+            let now = Instant::now();
+            if now >= deadline {
+                return false;
+            }
+            thread::sleep(cmp::min(deadline - now, POLL_INTERVAL));
+        }
+        true
+    }
+}
+
+
 //
 
 
@@ -56,7 +223,7 @@ pub(crate) struct NethunsSocketBase {
 pub struct RecvPacket<'a> {
     id: usize,
     pkthdr: &'a dyn PkthdrTrait,
-    buffer: &'a [u8],
+    buffer: &'a mut [u8],
     /// Reference used to set the status flag of the corresponding ring slot
     /// to `Free` when the `RecPacketData` is dropped.
     slot_status_flag: &'a AtomicRingSlotStatus,
@@ -67,7 +234,7 @@ impl<'a> RecvPacket<'a> {
     pub(super) fn new(
         id: usize,
         pkthdr: &'a dyn PkthdrTrait,
-        buffer: &'a [u8],
+        buffer: &'a mut [u8],
         slot_status_flag: &'a AtomicRingSlotStatus,
     ) -> Self {
         Self {
@@ -78,6 +245,10 @@ impl<'a> RecvPacket<'a> {
         }
     }
     
+    /// Get the id of the packet: it counts the received packets,
+    /// starting from 1, and the packet is stored in the slot
+    /// `(id - 1) % size` of the RX ring
+    /// (see [`NethunsSocket::slot_status`](crate::sockets::NethunsSocket::slot_status)).
     #[inline(always)]
     pub fn id(&self) -> usize {
         self.id
@@ -92,6 +263,93 @@ impl<'a> RecvPacket<'a> {
     pub fn buffer(&self) -> &[u8] {
         self.buffer
     }
+    
+    /// Get the packet buffer as a mutable slice, e.g. to rewrite
+    /// its headers in place before sending it again (zero-copy).
+    ///
+    /// The slot is held exclusively by the packet until it's dropped,
+    /// so the borrow checker prevents reading the buffer meanwhile.
+    /// The changes affect what is emitted by a subsequent
+    /// [`NethunsSocket::send()`](crate::sockets::NethunsSocket::send) or
+    /// [`NethunsSocketPcap::store()`](crate::sockets::pcap::NethunsSocketPcap::store)
+    /// of the packet, while its header (e.g. `len`) is left unchanged.
+    #[inline(always)]
+    pub fn buffer_mut(&mut self) -> &mut [u8] {
+        self.buffer
+    }
+    
+    /// Get the pcap record (header and payload) of the packet,
+    /// ready to be written with [`NethunsSocketPcap::write()`](crate::sockets::pcap::NethunsSocketPcap::write).
+    ///
+    /// The payload is copied only if the VLAN tag has been offloaded
+    /// by the NIC and it must be reinserted in the packet.
+    #[inline(always)]
+    pub fn as_pcap_record(&self) -> (nethuns_pcap_pkthdr, Cow<[u8]>) {
+        nethuns_pcap_record(self.pkthdr, self.buffer)
+    }
+    
+    /// Consume the packet, returning its pcap record header
+    /// and an owned copy of its payload.
+    ///
+    /// The ring slot is released (exactly once) as soon as the copy
+    /// is made, so it's the natural terminal operation for packets
+    /// which leave the capture thread (e.g. through a channel).
+    /// Like [`as_pcap_record()`](Self::as_pcap_record), an offloaded
+    /// VLAN tag is reinserted in the payload.
+    pub fn into_vec(self) -> (nethuns_pcap_pkthdr, Vec<u8>) {
+        let (header, payload) = self.as_pcap_record();
+        (header, payload.into_owned())
+        // `self` is dropped here, releasing the slot
+    }
+    
+    /// Get the number of packets lost immediately before this one
+    /// (e.g. dropped by the kernel because the ring was full).
+    ///
+    /// It lets loss-aware applications attribute gaps to specific points
+    /// of a flow, unlike the aggregate `rx_dropped` statistic.
+    /// It's always 0 if the underlying I/O framework doesn't provide
+    /// per-packet loss information (e.g. netmap).
+    #[inline(always)]
+    pub fn gap(&self) -> u32 {
+        self.pkthdr.gap()
+    }
+    
+    /// Get the flags of the netmap slot which contained the packet
+    /// (e.g. [`NetmapFlags::MOREFRAG`] if the packet continues
+    /// in the next slot).
+    ///
+    /// The set is empty if the packet wasn't received through netmap.
+    #[inline(always)]
+    pub fn netmap_flags(&self) -> NetmapFlags {
+        self.pkthdr.netmap_flags()
+    }
+    
+    /// Get the direction of the packet, i.e. whether it was received
+    /// from the network or transmitted by the local host
+    /// (captured only if the `host_rings` option is set and the `dir`
+    /// option includes the out direction).
+    #[inline(always)]
+    pub fn direction(&self) -> PacketDirection {
+        self.pkthdr.direction()
+    }
+    
+    /// Get the capture timestamp of the packet, as time elapsed
+    /// since the UNIX epoch.
+    ///
+    /// The precision (microseconds or nanoseconds) is the one
+    /// provided by the underlying I/O framework or pcap file.
+    #[inline(always)]
+    pub fn timestamp_duration(&self) -> Duration {
+        pkthdr_timestamp(self.pkthdr)
+    }
+    
+    /// Get the capture timestamp of the packet.
+    ///
+    /// See [`timestamp_duration()`](Self::timestamp_duration).
+    #[inline(always)]
+    pub fn timestamp(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + self.timestamp_duration()
+    }
 }
 
 
@@ -107,6 +365,18 @@ impl Display for RecvPacket<'_> {
     }
 }
 
+/// Two packets are equal if they have the same payload, length
+/// and captured length.
+///
+/// The timestamps and the slot ids are deliberately ignored, so that
+/// e.g. a packet sent and then captured on the loopback interface
+/// compares equal to the original one.
+impl PartialEq for RecvPacket<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        packets_eq(self.pkthdr, self.buffer, other.pkthdr, other.buffer)
+    }
+}
+
 impl Drop for RecvPacket<'_> {
     /// Release the buffer by resetting the status flag of
     /// the corresponding ring slot.
@@ -115,3 +385,429 @@ impl Drop for RecvPacket<'_> {
             .store(RingSlotStatus::Free, atomic::Ordering::Release);
     }
 }
+
+
+/// Public data structure for a packet inspected when calling [`NethunsSocket::recv_peek()`](crate::sockets::NethunsSocket::recv_peek).
+///
+/// Unlike [`RecvPacket`], it doesn't consume the packet, which will be
+/// returned again by the next call to [`NethunsSocket::recv()`](crate::sockets::NethunsSocket::recv).
+#[derive(Debug)]
+pub struct PeekedPacket<'a> {
+    pkthdr: &'a dyn PkthdrTrait,
+    buffer: &'a [u8],
+}
+
+
+impl<'a> PeekedPacket<'a> {
+    pub(super) fn new(pkthdr: &'a dyn PkthdrTrait, buffer: &'a [u8]) -> Self {
+        Self { pkthdr, buffer }
+    }
+    
+    #[inline(always)]
+    pub fn pkthdr(&self) -> &dyn PkthdrTrait {
+        self.pkthdr
+    }
+    
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        self.buffer
+    }
+}
+
+
+/// Public data structure for a packet received when calling [`NethunsSocket::recv_owned()`](crate::sockets::NethunsSocket::recv_owned).
+///
+/// Unlike [`RecvPacket`], it owns a copy of the packet, so it doesn't
+/// borrow the socket and the corresponding ring slot is already released.
+#[derive(Clone, Debug)]
+pub struct OwnedPacket {
+    id: usize,
+    pkthdr: Pkthdr,
+    buffer: Vec<u8>,
+}
+
+
+impl OwnedPacket {
+    pub(super) fn new(id: usize, pkthdr: Pkthdr, buffer: Vec<u8>) -> Self {
+        Self { id, pkthdr, buffer }
+    }
+    
+    #[inline(always)]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    
+    #[inline(always)]
+    pub fn pkthdr(&self) -> &dyn PkthdrTrait {
+        &self.pkthdr
+    }
+    
+    #[inline(always)]
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+    
+    /// Consume the packet and return its payload.
+    #[inline(always)]
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.buffer
+    }
+    
+    /// Consume the packet and return its payload as a reference-counted,
+    /// cheaply cloneable [`bytes::Bytes`] buffer (`bytes` feature),
+    /// e.g. to send it through channels or async codecs.
+    ///
+    /// The ring slot can't back a `Bytes` buffer safely, so the payload
+    /// is copied exactly once, when the packet is received
+    /// (see [`NethunsSocket::recv_owned`](crate::sockets::NethunsSocket::recv_owned)):
+    /// this conversion takes ownership of that copy.
+    #[cfg(feature = "bytes")]
+    #[inline(always)]
+    pub fn into_bytes(self) -> bytes::Bytes {
+        bytes::Bytes::from(self.buffer)
+    }
+    
+    /// Get the pcap record (header and payload) of the packet,
+    /// ready to be written with [`NethunsSocketPcap::write()`](crate::sockets::pcap::NethunsSocketPcap::write).
+    #[inline(always)]
+    pub fn as_pcap_record(&self) -> (nethuns_pcap_pkthdr, Cow<[u8]>) {
+        nethuns_pcap_record(&self.pkthdr, &self.buffer)
+    }
+    
+    /// Get the capture timestamp of the packet, as time elapsed
+    /// since the UNIX epoch.
+    #[inline(always)]
+    pub fn timestamp_duration(&self) -> Duration {
+        pkthdr_timestamp(&self.pkthdr)
+    }
+    
+    /// Get the capture timestamp of the packet.
+    #[inline(always)]
+    pub fn timestamp(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + self.timestamp_duration()
+    }
+}
+
+
+/// Two packets are equal if they have the same payload, length
+/// and captured length (see [`RecvPacket`]): the timestamps
+/// and the slot ids are ignored.
+impl PartialEq for OwnedPacket {
+    fn eq(&self, other: &Self) -> bool {
+        packets_eq(&self.pkthdr, &self.buffer, &other.pkthdr, &other.buffer)
+    }
+}
+
+
+/// Compare two packets by payload, length and captured length.
+#[inline(always)]
+fn packets_eq(
+    pkthdr1: &dyn PkthdrTrait,
+    buffer1: &[u8],
+    pkthdr2: &dyn PkthdrTrait,
+    buffer2: &[u8],
+) -> bool {
+    pkthdr1.len() == pkthdr2.len()
+        && pkthdr1.snaplen() == pkthdr2.snaplen()
+        && buffer1 == buffer2
+}
+
+
+/// Assemble the timestamp stored in a packet header.
+///
+/// `tstamp_nsec` is used since it carries the full precision of the
+/// timestamp, whether it was captured in microseconds or nanoseconds.
+#[inline(always)]
+fn pkthdr_timestamp(pkthdr: &dyn PkthdrTrait) -> Duration {
+    Duration::new(pkthdr.tstamp_sec() as _, pkthdr.tstamp_nsec())
+}
+
+
+/// Compare two packet headers by capture timestamp (seconds first,
+/// then nanoseconds), e.g. to sort or k-way merge the packets captured
+/// by multiple sockets (one per queue) into a single time-ordered sequence.
+///
+/// The timestamps are compared with nanosecond precision: those captured
+/// with microsecond precision are scaled to nanoseconds, so headers
+/// of mixed precision are ordered consistently.
+///
+/// # Example
+/// ```
+/// use nethuns::sockets::{by_timestamp, OwnedPacket};
+///
+/// fn sort_packets(packets: &mut [OwnedPacket]) {
+///     packets.sort_by(|a, b| by_timestamp(a.pkthdr(), b.pkthdr()));
+/// }
+/// ```
+#[inline(always)]
+pub fn by_timestamp(
+    pkthdr1: &dyn PkthdrTrait,
+    pkthdr2: &dyn PkthdrTrait,
+) -> cmp::Ordering {
+    pkthdr_timestamp(pkthdr1).cmp(&pkthdr_timestamp(pkthdr2))
+}
+
+
+/// Batch of [`RecvPacket`]s which releases all the corresponding
+/// ring slots when it goes out of scope.
+///
+/// It prevents the ring from stalling when an early `return` or `?`
+/// leaves some received packets unreleased.
+/// The batch can be used as a slice of [`RecvPacket`] thanks to [`Deref`].
+#[derive(Debug, Default)]
+pub struct PacketBatch<'a> {
+    packets: Vec<RecvPacket<'a>>,
+}
+
+
+impl<'a> PacketBatch<'a> {
+    /// Create a new empty `PacketBatch`.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self {
+            packets: Vec::new(),
+        }
+    }
+    
+    /// Create a new empty `PacketBatch`
+    /// able to hold `capacity` packets without reallocating.
+    #[inline(always)]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            packets: Vec::with_capacity(capacity),
+        }
+    }
+    
+    /// Add a received packet to the batch.
+    #[inline(always)]
+    pub fn push(&mut self, packet: RecvPacket<'a>) {
+        self.packets.push(packet);
+    }
+    
+    /// Release all the packets in the batch,
+    /// setting the corresponding ring slots as `Free`.
+    #[inline(always)]
+    pub fn release_all(self) {
+        drop(self);
+    }
+}
+
+
+impl<'a> Deref for PacketBatch<'a> {
+    type Target = [RecvPacket<'a>];
+    
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.packets
+    }
+}
+
+
+impl<'a> FromIterator<RecvPacket<'a>> for PacketBatch<'a> {
+    fn from_iter<I: IntoIterator<Item = RecvPacket<'a>>>(iter: I) -> Self {
+        Self {
+            packets: iter.into_iter().collect(),
+        }
+    }
+}
+
+
+impl<'a> Extend<RecvPacket<'a>> for PacketBatch<'a> {
+    fn extend<I: IntoIterator<Item = RecvPacket<'a>>>(&mut self, iter: I) {
+        self.packets.extend(iter);
+    }
+}
+
+
+/// Logical packet received when calling [`NethunsSocket::recv_fragmented()`](crate::sockets::NethunsSocket::recv_fragmented),
+/// made of the [`RecvPacket`]s of its fragments (one per ring slot).
+///
+/// Normal packets have a single fragment.
+/// The fragments can be accessed as a slice of [`RecvPacket`] thanks
+/// to [`Deref`], and their slots are released when it goes out of scope.
+#[derive(Debug)]
+pub struct FragmentedPacket<'a> {
+    fragments: Vec<RecvPacket<'a>>,
+}
+
+
+impl<'a> FragmentedPacket<'a> {
+    pub(super) fn new(fragments: Vec<RecvPacket<'a>>) -> Self {
+        debug_assert!(!fragments.is_empty());
+        Self { fragments }
+    }
+    
+    /// Get the buffers of the fragments, in order.
+    #[inline(always)]
+    pub fn buffers(&self) -> impl Iterator<Item = &[u8]> {
+        self.fragments.iter().map(RecvPacket::buffer)
+    }
+    
+    /// Get the total number of bytes of the fragments.
+    #[inline(always)]
+    pub fn total_len(&self) -> usize {
+        self.buffers().map(<[u8]>::len).sum()
+    }
+    
+    /// Copy the fragments into a contiguous buffer.
+    pub fn to_contiguous(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(self.total_len());
+        self.buffers().for_each(|b| buffer.extend_from_slice(b));
+        buffer
+    }
+}
+
+
+impl<'a> Deref for FragmentedPacket<'a> {
+    type Target = [RecvPacket<'a>];
+    
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.fragments
+    }
+}
+
+
+//
+
+
+/// Writer for the buffer of a specific slot of the TX ring,
+/// obtained by calling [`NethunsSocket::tx_slot_writer()`](crate::sockets::NethunsSocket::tx_slot_writer).
+///
+/// It keeps track of the number of bytes written into the buffer,
+/// and it marks the slot as *ready for transmission* with the exact written
+/// length when [`flush()`](io::Write::flush) is called or when it's dropped,
+/// so that there's no need to call
+/// [`NethunsSocket::send_slot()`](crate::sockets::NethunsSocket::send_slot) manually.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct TxSlotWriter<'a> {
+    #[derivative(Debug = "ignore")]
+    socket: &'a NethunsSocket,
+    id: usize,
+    #[derivative(Debug = "ignore")]
+    buffer: &'a mut [u8],
+    written: usize,
+    submitted: bool,
+}
+
+
+impl<'a> TxSlotWriter<'a> {
+    pub(super) fn new(
+        socket: &'a NethunsSocket,
+        id: usize,
+        buffer: &'a mut [u8],
+    ) -> Self {
+        Self {
+            socket,
+            id,
+            buffer,
+            written: 0,
+            submitted: false,
+        }
+    }
+    
+    /// Get the id of the TX ring slot.
+    #[inline(always)]
+    pub fn id(&self) -> usize {
+        self.id
+    }
+    
+    /// Get the number of bytes written so far into the slot buffer.
+    #[inline(always)]
+    pub fn written(&self) -> usize {
+        self.written
+    }
+    
+    /// Mark the slot as *ready for transmission*, if not done yet.
+    fn submit(&mut self) -> Result<(), NethunsSendError> {
+        if !self.submitted {
+            self.socket.send_slot(self.id, self.written)?;
+            self.submitted = true;
+        }
+        Ok(())
+    }
+}
+
+
+impl io::Write for TxSlotWriter<'_> {
+    /// Copy `buf` into the slot buffer, after the bytes already written.
+    ///
+    /// It fails if the slot has already been marked as *ready for transmission*.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.submitted {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "slot already marked for transmission",
+            ));
+        }
+        let n = cmp::min(buf.len(), self.buffer.len() - self.written);
+        self.buffer[self.written..self.written + n]
+            .copy_from_slice(&buf[..n]);
+        self.written += n;
+        Ok(n)
+    }
+    
+    /// Mark the slot as *ready for transmission* with the written length.
+    fn flush(&mut self) -> io::Result<()> {
+        self.submit().map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+}
+
+
+impl Drop for TxSlotWriter<'_> {
+    /// Mark the slot as *ready for transmission*, if some bytes have been
+    /// written and the writer hasn't been flushed yet.
+    fn drop(&mut self) {
+        if self.written > 0 {
+            if let Err(e) = self.submit() {
+                nethuns_error!("[TxSlotWriter::Drop] couldn't send slot: {e}");
+            }
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_packet_dedup() {
+        let mut dedup = PacketDedup::default();
+        
+        // Consecutive duplicates are suppressed...
+        assert!(!dedup.is_duplicate(&[1, 2, 3]));
+        assert!(dedup.is_duplicate(&[1, 2, 3]));
+        assert!(dedup.is_duplicate(&[1, 2, 3]));
+        
+        // ...but non-consecutive ones aren't
+        assert!(!dedup.is_duplicate(&[4, 5]));
+        assert!(!dedup.is_duplicate(&[1, 2, 3]));
+        assert!(!dedup.is_duplicate(&[]));
+        assert!(dedup.is_duplicate(&[]));
+        
+        assert_eq!(dedup.deduped, 3);
+    }
+    
+    #[test]
+    fn test_pause_handle_wait_resumed() {
+        let handle = PauseHandle::default();
+        assert!(handle.wait_resumed(Instant::now()));
+        
+        handle.pause();
+        let start = Instant::now();
+        assert!(!handle.wait_resumed(start + Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        
+        // Resumed from another thread while waiting
+        let remote = handle.clone();
+        let resumer = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            remote.resume();
+        });
+        assert!(handle.wait_resumed(Instant::now() + Duration::from_secs(10)));
+        assert!(!handle.is_paused());
+        resumer.join().unwrap();
+    }
+}
@@ -41,12 +41,33 @@ pub enum NethunsRecvError {
     NoPacketsAvailable,
     #[error("[recv] the received packet has been filtered out")]
     PacketFiltered,
+    #[error("[recv] the filter panicked: the packet has been dropped")]
+    FilterPanicked,
+    #[error("[recv] the device bound to the socket doesn't exist anymore")]
+    InterfaceGone,
+    #[error("[recv] the capture is paused")]
+    Paused,
     #[error("[recv] error of the I/O framework: {0}")]
     FrameworkError(String),
     #[error("[recv] an unexpected error occurred: {0}")]
     Error(String),
 }
 
+impl NethunsRecvError {
+    /// Check if the error is transient, i.e. the operation can be
+    /// retried later (ring in use, no packets available, packet filtered,
+    /// capture paused).
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::InUse
+                | Self::NoPacketsAvailable
+                | Self::PacketFiltered
+                | Self::Paused
+        )
+    }
+}
+
 
 /// Error type for [`NethunsSocket::send`](crate::sockets::NethunsSocket::send)
 #[derive(Debug, Error)]
@@ -59,10 +80,24 @@ pub enum NethunsSendError {
     InvalidPacketSize(usize, usize),
     #[error("[send] ring in use")]
     InUse,
+    #[error("[send] failed to flush the TX ring: {0}")]
+    Flush(#[from] NethunsFlushError),
     #[error("[send] an unexpected error occurred: {0}")]
     Error(String),
 }
 
+impl NethunsSendError {
+    /// Check if the error is transient, i.e. the operation can be
+    /// retried later (ring in use).
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::InUse => true,
+            Self::Flush(e) => e.is_transient(),
+            _ => false,
+        }
+    }
+}
+
 
 /// Error type for [`NethunsSocket::flush`](crate::sockets::NethunsSocket::flush)
 #[derive(Debug, Error)]
@@ -73,16 +108,73 @@ pub enum NethunsFlushError {
     InUse,
     #[error("[flush] failed transmission: {0}")]
     FailedTransmission(String),
+    #[error("[flush] the device bound to the socket doesn't exist anymore")]
+    InterfaceGone,
     #[error("[recv] error of the I/O framework: {0}")]
     FrameworkError(String),
     #[error("[flush] an unexpected error occurred: {0}")]
     Error(String),
 }
 
+impl NethunsFlushError {
+    /// Check if the error is transient, i.e. the operation can be
+    /// retried later (ring in use).
+    pub fn is_transient(&self) -> bool {
+        matches!(self, Self::InUse)
+    }
+}
+
+
+/// Error type for [`NethunsSocket::add_flow_rule`](crate::sockets::NethunsSocket::add_flow_rule)
+/// and [`NethunsSocket::remove_flow_rule`](crate::sockets::NethunsSocket::remove_flow_rule)
+#[derive(Debug, Error)]
+pub enum NethunsFlowRuleError {
+    #[error("[flow_rule] operation not supported: {0}")]
+    NotSupported(String),
+    #[error("[flow_rule] socket not bound to a device")]
+    NotBound,
+    #[error("[flow_rule] error during ioctl: {0}")]
+    Error(#[from] io::Error),
+}
+
+
+/// Error type for [`NethunsSocket::attach_bpf`](crate::sockets::NethunsSocket::attach_bpf)
+/// and [`NethunsSocket::detach_bpf`](crate::sockets::NethunsSocket::detach_bpf)
+#[derive(Debug, Error)]
+pub enum NethunsBpfError {
+    #[error("[bpf] operation not supported: {0}")]
+    NotSupported(String),
+    #[error("[bpf] invalid program: {0}")]
+    InvalidProgram(String),
+    #[error("[bpf] the kernel rejected the request: {0}")]
+    Error(#[from] io::Error),
+}
+
+
+/// Error type for [`NethunsSocket::verify_queue_binding`](crate::sockets::NethunsSocket::verify_queue_binding)
+#[derive(Debug, Error)]
+pub enum NethunsQueueBindingError {
+    #[error("[verify_queue_binding] requested queue {0}, but the socket is bound to {1}")]
+    Mismatch(String, String),
+}
+
+
+/// Error type for [`NethunsSocket::dump_rings`](crate::sockets::NethunsSocket::dump_rings)
+#[derive(Debug, Error)]
+pub enum NethunsDumpRingsError {
+    /// Reserved for backends that query the I/O framework:
+    /// the netmap backend never returns it.
+    #[error("[dump_rings] error of the I/O framework: {0}")]
+    FrameworkError(String),
+}
+
 
 /// Error type for [`NethunsSocketPcap::open`](crate::sockets::pcap::NethunsSocketPcap::open)
 #[derive(Debug, Error)]
 pub enum NethunsPcapOpenError {
+    #[error("[pcap_open] snaplen of the pcap file ({snaplen}) greater than packetsize ({packetsize}) with `strict_snaplen` option set")]
+    SnaplenTooLarge { snaplen: u32, packetsize: u32 },
+    
     // STANDARD_PCAP_READER
     #[error("[pcap_open] could not open pcap file for writing (enable `NETHUNS_USE_BUILTIN_PCAP_READER` feature to use builtin pcap reader)")]
     WriteModeNotSupported,
@@ -121,6 +213,12 @@ pub enum NethunsPcapReadError {
     FileError(io::Error),
     #[error("[pcap_read] end of file")]
     Eof,
+    #[error(
+        "[pcap_read] truncated packet record: expected {0} bytes, got {1} bytes"
+    )]
+    Truncated(usize, usize),
+    #[error("[pcap_read] malformed packet record: {0}")]
+    MalformedRecord(String),
 }
 
 impl<I> From<pcap_parser::PcapError<I>> for NethunsPcapReadError
@@ -0,0 +1,235 @@
+//! Hardware flow rules (ethtool ntuple filters / flow director).
+//!
+//! A flow rule instructs the NIC to steer the packets matching it
+//! to a specific RX queue, so that the filtering is offloaded to the
+//! hardware instead of being performed for each packet by a
+//! [`NethunsFilter`](crate::types::NethunsFilter).
+
+use std::ffi::CStr;
+use std::net::Ipv4Addr;
+use std::{io, mem};
+
+use rustix::fd::AsRawFd;
+use rustix::net;
+
+use super::errors::NethunsFlowRuleError;
+
+
+// Constants from `linux/ethtool.h`
+const ETHTOOL_SRXCLSRLDEL: u32 = 0x00000031;
+const ETHTOOL_SRXCLSRLINS: u32 = 0x00000032;
+const TCP_V4_FLOW: u32 = 0x01;
+const UDP_V4_FLOW: u32 = 0x02;
+const RX_CLS_LOC_ANY: u32 = 0xffffffff;
+
+
+/// Transport protocol matched by a [`FlowRule`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FlowProto {
+    #[default]
+    Tcp,
+    Udp,
+}
+
+
+/// Hardware flow rule, which steers the matching IPv4 packets
+/// to the RX queue `queue` of the device.
+///
+/// The fields set to `None` match any value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlowRule {
+    pub proto: FlowProto,
+    pub src_ip: Option<Ipv4Addr>,
+    pub dst_ip: Option<Ipv4Addr>,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+    /// RX queue which the matching packets are steered to.
+    pub queue: u32,
+}
+
+
+/// Handle of a hardware flow rule installed by
+/// [`NethunsSocket::add_flow_rule`](crate::sockets::NethunsSocket::add_flow_rule).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RuleHandle(u32);
+
+impl RuleHandle {
+    /// Location of the rule in the classification table of the device.
+    #[inline(always)]
+    pub fn location(&self) -> u32 {
+        self.0
+    }
+}
+
+
+/// `struct ethtool_rx_flow_spec` from `linux/ethtool.h`.
+///
+/// The `h_u`/`m_u` unions are laid out as raw bytes, since only
+/// `struct ethtool_tcpip4_spec` is needed.
+#[repr(C)]
+struct EthtoolRxFlowSpec {
+    flow_type: u32,
+    h_u: [u8; 52],
+    h_ext: [u8; 20],
+    m_u: [u8; 52],
+    m_ext: [u8; 20],
+    ring_cookie: u64,
+    location: u32,
+}
+
+
+/// `struct ethtool_rxnfc` from `linux/ethtool.h`
+/// (without the trailing flexible array).
+#[repr(C)]
+struct EthtoolRxnfc {
+    cmd: u32,
+    flow_type: u32,
+    data: u64,
+    fs: EthtoolRxFlowSpec,
+    rule_cnt: u32,
+}
+
+// Make sure the layout matches the one of the kernel
+static_assertions::assert_eq_size!(EthtoolRxFlowSpec, [u8; 168]);
+static_assertions::assert_eq_size!(EthtoolRxnfc, [u8; 192]);
+
+
+impl EthtoolRxnfc {
+    /// Build the `ETHTOOL_SRXCLSRLINS` request for a flow rule.
+    fn insert(rule: &FlowRule) -> Self {
+        // [SAFETY] all-zero is a valid value for these plain C structs
+        let mut nfc: Self = unsafe { mem::zeroed() };
+        nfc.cmd = ETHTOOL_SRXCLSRLINS;
+        nfc.fs.flow_type = match rule.proto {
+            FlowProto::Tcp => TCP_V4_FLOW,
+            FlowProto::Udp => UDP_V4_FLOW,
+        };
+        
+        // `struct ethtool_tcpip4_spec`: fields in network byte order,
+        // mask bits set to 1 are matched against the packet.
+        let fields: [(usize, Option<Vec<u8>>); 4] = [
+            (0, rule.src_ip.map(|ip| ip.octets().to_vec())),
+            (4, rule.dst_ip.map(|ip| ip.octets().to_vec())),
+            (8, rule.src_port.map(|p| p.to_be_bytes().to_vec())),
+            (10, rule.dst_port.map(|p| p.to_be_bytes().to_vec())),
+        ];
+        for (offset, value) in fields {
+            if let Some(value) = value {
+                let range = offset..offset + value.len();
+                nfc.fs.h_u[range.clone()].copy_from_slice(&value);
+                nfc.fs.m_u[range].fill(0xff);
+            }
+        }
+        
+        nfc.fs.ring_cookie = rule.queue as _;
+        nfc.fs.location = RX_CLS_LOC_ANY;
+        nfc
+    }
+    
+    /// Build the `ETHTOOL_SRXCLSRLDEL` request for an installed rule.
+    fn delete(handle: RuleHandle) -> Self {
+        // [SAFETY] all-zero is a valid value for these plain C structs
+        let mut nfc: Self = unsafe { mem::zeroed() };
+        nfc.cmd = ETHTOOL_SRXCLSRLDEL;
+        nfc.fs.location = handle.0;
+        nfc
+    }
+}
+
+
+/// Install a hardware flow rule on the device.
+///
+/// # Returns
+/// * `Ok(RuleHandle)` - The handle of the installed rule.
+/// * `Err(NethunsFlowRuleError::NotSupported)` - If the driver doesn't support ntuple filters (or they are disabled).
+/// * `Err(NethunsFlowRuleError::Error)` - If the `ioctl` system call failed.
+pub(crate) fn nethuns_flow_rule_insert(
+    devname: &CStr,
+    rule: &FlowRule,
+) -> Result<RuleHandle, NethunsFlowRuleError> {
+    let mut nfc = EthtoolRxnfc::insert(rule);
+    ethtool_ioctl(devname, &mut nfc)?;
+    Ok(RuleHandle(nfc.fs.location))
+}
+
+
+/// Remove a hardware flow rule from the device.
+///
+/// # Returns
+/// * `Ok(())` - If the rule was removed.
+/// * `Err(NethunsFlowRuleError::NotSupported)` - If the driver doesn't support ntuple filters.
+/// * `Err(NethunsFlowRuleError::Error)` - If the `ioctl` system call failed.
+pub(crate) fn nethuns_flow_rule_delete(
+    devname: &CStr,
+    handle: RuleHandle,
+) -> Result<(), NethunsFlowRuleError> {
+    let mut nfc = EthtoolRxnfc::delete(handle);
+    ethtool_ioctl(devname, &mut nfc)
+}
+
+
+/// Call `ioctl(SIOCETHTOOL)` on the device with the given request.
+fn ethtool_ioctl(
+    devname: &CStr,
+    nfc: &mut EthtoolRxnfc,
+) -> Result<(), NethunsFlowRuleError> {
+    let socket =
+        net::socket(net::AddressFamily::INET, net::SocketType::DGRAM, None)
+            .map_err(io::Error::from)?;
+    
+    let mut ifr: libc::ifreq = unsafe { mem::zeroed() };
+    devname
+        .to_bytes()
+        .iter()
+        .take(ifr.ifr_name.len() - 1)
+        .enumerate()
+        .for_each(|(i, c)| {
+            ifr.ifr_name[i] = *c as _;
+        });
+    ifr.ifr_ifru.ifru_data = nfc as *mut EthtoolRxnfc as _;
+    
+    let ret = unsafe {
+        libc::ioctl(socket.as_raw_fd(), libc::SIOCETHTOOL, &mut ifr)
+    };
+    if ret < 0 {
+        let e = io::Error::last_os_error();
+        return Err(match e.raw_os_error() {
+            Some(libc::EOPNOTSUPP) => NethunsFlowRuleError::NotSupported(
+                format!("device {devname:?} doesn't support ntuple filters"),
+            ),
+            _ => NethunsFlowRuleError::Error(e),
+        });
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_flow_rule_encoding() {
+        let rule = FlowRule {
+            proto: FlowProto::Udp,
+            dst_ip: Some(Ipv4Addr::new(10, 0, 0, 1)),
+            dst_port: Some(443),
+            queue: 3,
+            ..Default::default()
+        };
+        let nfc = EthtoolRxnfc::insert(&rule);
+        
+        assert_eq!(nfc.cmd, ETHTOOL_SRXCLSRLINS);
+        assert_eq!(nfc.fs.flow_type, UDP_V4_FLOW);
+        assert_eq!(nfc.fs.ring_cookie, 3);
+        assert_eq!(nfc.fs.location, RX_CLS_LOC_ANY);
+        // Source address and port are wildcards
+        assert_eq!(&nfc.fs.m_u[0..4], &[0; 4]);
+        assert_eq!(&nfc.fs.m_u[8..10], &[0; 2]);
+        // Destination address and port are matched
+        assert_eq!(&nfc.fs.h_u[4..8], &[10, 0, 0, 1]);
+        assert_eq!(&nfc.fs.m_u[4..8], &[0xff; 4]);
+        assert_eq!(&nfc.fs.h_u[10..12], &443_u16.to_be_bytes());
+        assert_eq!(&nfc.fs.m_u[10..12], &[0xff; 2]);
+    }
+}
@@ -4,10 +4,13 @@ mod constants;
 
 
 use core::fmt::Debug;
+use std::borrow::Cow;
 use std::cell::UnsafeCell;
+use std::{cmp, io};
 
 use cfg_if::cfg_if;
 use derivative::Derivative;
+use etherparse::{InternetSlice, SlicedPacket, TransportSlice};
 use getset::CopyGetters;
 
 use crate::sockets::errors::{
@@ -37,6 +40,12 @@ static_assertions::assert_not_impl_any!(NethunsSocketPcap: Sync);
 impl NethunsSocketPcap {
     /// Open the socket for reading captured packets from a file.
     ///
+    /// With the `mmap` feature, a regular file opened for reading is
+    /// memory-mapped: it must not be truncated while the socket is open
+    /// (e.g. rotated by `tcpdump -w` while it's still being written),
+    /// otherwise reading the truncated pages kills the process
+    /// with `SIGBUS`. Data appended after the opening is not read.
+    ///
     /// # Arguments
     /// * `opt`: socket options
     /// * `filename`: name of the pcap file
@@ -48,6 +57,7 @@ impl NethunsSocketPcap {
     /// * `Err(NethunsPcapOpenError::PcapError)` - if an error occurs while parsing the pcap file (STANDARD_PCAP_READER only).
     /// * `Err(NethunsPcapOpenError::FileError)` - if an error occurs while accessing the file (BUILTIN_PCAP_READER only).
     /// * `Err(NethunsPcapOpenError::MagicNotSupported)` - if the format of the pcap file is not supported (BUILTIN_PCAP_READER only).
+    /// * `Err(NethunsPcapOpenError::SnaplenTooLarge)` - if the `strict_snaplen` option is set and the snaplen of the pcap file is greater than `packetsize`.
     pub fn open(
         opt: NethunsSocketOptions,
         filename: &str,
@@ -68,7 +78,9 @@ impl NethunsSocketPcap {
     /// * `Err(NethunsPcapReadError::InUse)` - if the ring buffer of the nethuns base socket is full.
     /// * `Err(NethunsPcapOpenError::PcapError)` - if an error occurs while parsing the pcap file (STANDARD_PCAP_READER only).
     /// * `Err(NethunsPcapOpenError::FileError)` - if an error occurs while accessing the file (BUILTIN_PCAP_READER only).
-    /// * `Err(NethunsPcapOpenError::Eof)` - if the end of the file is reached.
+    /// * `Err(NethunsPcapOpenError::Eof)` - if the end of the file is reached at a record boundary.
+    /// * `Err(NethunsPcapReadError::Truncated)` - if the file ends in the middle of a packet record (BUILTIN_PCAP_READER only).
+    /// * `Err(NethunsPcapReadError::MalformedRecord)` - if the lengths declared in the header of a packet record are inconsistent (BUILTIN_PCAP_READER only).
     pub fn read(&self) -> Result<RecvPacket, NethunsPcapReadError> {
         unsafe { (*UnsafeCell::get(&self.inner)).read() }
     }
@@ -121,8 +133,81 @@ impl NethunsSocketPcap {
     pub fn rewind(&self) -> Result<u64, NethunsPcapRewindError> {
         unsafe { (*UnsafeCell::get(&self.inner)).rewind() }
     }
+    
+    
+    /// Create an independent reader of the same pcap file, e.g. to scan
+    /// a large capture in parallel passes.
+    ///
+    /// The new reader has its own position, starting at the first packet
+    /// of the file, and its own ring of packets: reading from one of them
+    /// doesn't affect the other. The file is opened again through
+    /// the descriptor of this reader, so it's not required to still
+    /// exist under its original name.
+    ///
+    /// # Returns
+    /// * `Ok(NethunsSocketPcap)` - the new reader.
+    /// * `Err(io::Error)` with kind `Unsupported` - if the socket is opened in writing mode.
+    /// * `Err(io::Error)` - if the file couldn't be opened again.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        let inner = unsafe { &*UnsafeCell::get(&self.inner) };
+        inner.try_clone().map(|inner| Self {
+            inner: UnsafeCell::new(inner),
+        })
+    }
+    
+    
+    /// Take the error occurred while writing packets through
+    /// [`Extend::extend`], if any.
+    ///
+    /// # Returns
+    /// * `Some(NethunsPcapWriteError)` - the error which stopped the last `extend` call.
+    /// * `None` - if no error occurred.
+    pub fn take_error(&mut self) -> Option<NethunsPcapWriteError> {
+        self.inner.get_mut().write_error.take()
+    }
+    
+    
+    /// Get the statistics of the packets read so far,
+    /// including the number of corrupt packets if the
+    /// `verify_checksums` option is set.
+    pub fn reader_stats(&self) -> PcapReaderStats {
+        unsafe { (*UnsafeCell::get(&self.inner)).reader_stats }
+    }
+    
+    
+    /// Get the snaplen declared in the header of the pcap file,
+    /// i.e. the maximum number of bytes captured for each packet.
+    ///
+    /// Packets longer than the `packetsize` option are truncated on read
+    /// (unless the `strict_snaplen` option is set, which makes
+    /// [`NethunsSocketPcap::open`] fail): it can be used to size
+    /// `packetsize` to read the packets in full.
+    pub fn file_snaplen(&self) -> u32 {
+        unsafe { (*UnsafeCell::get(&self.inner)).file_snaplen }
+    }
 }
 
+impl Extend<(nethuns_pcap_pkthdr, Vec<u8>)> for NethunsSocketPcap {
+    /// Write multiple packets already in pcap format to a pcap file
+    /// (see [`NethunsSocketPcap::write`]).
+    ///
+    /// The writing stops at the first error, which can be retrieved
+    /// by calling [`NethunsSocketPcap::take_error`].
+    fn extend<I: IntoIterator<Item = (nethuns_pcap_pkthdr, Vec<u8>)>>(
+        &mut self,
+        iter: I,
+    ) {
+        let inner = self.inner.get_mut();
+        for (header, packet) in iter {
+            if let Err(e) = inner.write(&header, &packet) {
+                inner.write_error = Some(e);
+                break;
+            }
+        }
+    }
+}
+
+
 /// Inner struct of the nethuns socket for packet capture (PCAP).
 /// It implements the [`NethunsSocketPcapTrait`] trait.
 ///
@@ -139,7 +224,19 @@ struct NethunsSocketPcapInner {
     reader: PcapReaderType,
     
     snaplen: u32,
+    /// Snaplen declared in the file header
+    file_snaplen: u32,
     magic: u32,
+    /// Whether the pcap file has (or is written in)
+    /// the opposite byte order of the host
+    swapped: bool,
+    /// Whether the file has been opened for writing
+    writing_mode: bool,
+    
+    reader_stats: PcapReaderStats,
+    
+    /// Error occurred while writing packets through [`Extend::extend`]
+    write_error: Option<NethunsPcapWriteError>,
 }
 
 static_assertions::assert_impl_all!(
@@ -164,6 +261,7 @@ trait NethunsSocketPcapTrait: Debug {
     /// * `Err(NethunsPcapOpenError::PcapError)` - if an error occurs while parsing the pcap file (STANDARD_PCAP_READER only).
     /// * `Err(NethunsPcapOpenError::FileError)` - if an error occurs while accessing the file (BUILTIN_PCAP_READER only).
     /// * `Err(NethunsPcapOpenError::MagicNotSupported)` - if the format of the pcap file is not supported (BUILTIN_PCAP_READER only).
+    /// * `Err(NethunsPcapOpenError::SnaplenTooLarge)` - if the `strict_snaplen` option is set and the snaplen of the pcap file is greater than `packetsize`.
     fn open(
         opt: NethunsSocketOptions,
         filename: &str,
@@ -180,7 +278,9 @@ trait NethunsSocketPcapTrait: Debug {
     /// * `Err(NethunsPcapReadError::InUse)` - if the ring buffer of the nethuns base socket is full.
     /// * `Err(NethunsPcapOpenError::PcapError)` - if an error occurs while parsing the pcap file (STANDARD_PCAP_READER only).
     /// * `Err(NethunsPcapOpenError::FileError)` - if an error occurs while accessing the file (BUILTIN_PCAP_READER only).
-    /// * `Err(NethunsPcapOpenError::Eof)` - if the end of the file is reached.
+    /// * `Err(NethunsPcapOpenError::Eof)` - if the end of the file is reached at a record boundary.
+    /// * `Err(NethunsPcapReadError::Truncated)` - if the file ends in the middle of a packet record (BUILTIN_PCAP_READER only).
+    /// * `Err(NethunsPcapReadError::MalformedRecord)` - if the lengths declared in the header of a packet record are inconsistent (BUILTIN_PCAP_READER only).
     fn read(&mut self) -> Result<RecvPacket, NethunsPcapReadError>;
     
     
@@ -225,6 +325,18 @@ trait NethunsSocketPcapTrait: Debug {
     /// * `Err(NethunsPcapRewindError::NotSupported)` - if the `NETHUNS_USE_BUILTIN_PCAP_READER` feature is not enabled (STANDARD_PCAP_READER only).
     /// * `Err(NethunsPcapRewindError::FileError)` - if an I/O error occurs while accessing the file (BUILTIN_PCAP_READER only).
     fn rewind(&mut self) -> Result<u64, NethunsPcapRewindError>;
+    
+    
+    /// Create an independent reader of the same pcap file,
+    /// with its own position (at the first packet) and its own ring.
+    ///
+    /// # Returns
+    /// * `Ok(Self)` - the new reader.
+    /// * `Err(io::Error)` with kind `Unsupported` - if the file is opened for writing.
+    /// * `Err(io::Error)` - if the file couldn't be opened again.
+    fn try_clone(&self) -> io::Result<Self>
+    where
+        Self: Sized;
 }
 
 
@@ -232,6 +344,8 @@ trait NethunsSocketPcapTrait: Debug {
 // according to the `NETHUNS_USE_BUILTIN_PCAP_READER` feature
 cfg_if!(
     if #[cfg(feature="NETHUNS_USE_BUILTIN_PCAP_READER")] {
+        #[cfg(feature = "mmap")]
+        mod mapped_file;
         mod reader_builtin;
         use reader_builtin::*;
     } else {
@@ -256,6 +370,202 @@ pub struct nethuns_pcap_pkthdr {
 }
 
 
+impl nethuns_pcap_pkthdr {
+    /// Build a new pcap packet header with a timestamp in microseconds.
+    ///
+    /// # Arguments
+    /// * `ts_sec`: seconds of the timestamp
+    /// * `ts_usec`: microseconds of the timestamp
+    /// * `caplen`: length of the portion of the packet present in the file
+    /// * `len`: length of the packet (off wire)
+    ///
+    /// # Returns
+    /// * `Some(nethuns_pcap_pkthdr)` - the new packet header.
+    /// * `None` - if `caplen` is greater than `len`.
+    pub fn new(
+        ts_sec: i64,
+        ts_usec: i64,
+        caplen: u32,
+        len: u32,
+    ) -> Option<Self> {
+        if caplen > len {
+            return None;
+        }
+        Some(Self {
+            ts: nethuns_pcap_timeval {
+                tv_sec: ts_sec,
+                tv_usec: ts_usec,
+            },
+            caplen,
+            len,
+        })
+    }
+    
+    /// Build a new pcap packet header with a timestamp in nanoseconds.
+    ///
+    /// The timestamp is stored with microsecond resolution,
+    /// since [`NethunsSocketPcap`] writes pcap files in the TCPDUMP format.
+    ///
+    /// # Arguments
+    /// * `ts_sec`: seconds of the timestamp
+    /// * `ts_nsec`: nanoseconds of the timestamp
+    /// * `caplen`: length of the portion of the packet present in the file
+    /// * `len`: length of the packet (off wire)
+    ///
+    /// # Returns
+    /// * `Some(nethuns_pcap_pkthdr)` - the new packet header.
+    /// * `None` - if `caplen` is greater than `len`.
+    pub fn with_nsec(
+        ts_sec: i64,
+        ts_nsec: i64,
+        caplen: u32,
+        len: u32,
+    ) -> Option<Self> {
+        Self::new(ts_sec, ts_nsec / 1000, caplen, len)
+    }
+}
+
+
+/// Build the pcap record (header and payload) of a packet received
+/// from a [`NethunsSocket`](crate::sockets::NethunsSocket).
+///
+/// If the VLAN tag has been offloaded by the NIC, it's reinserted
+/// in the payload after the MAC addresses, so that the payload
+/// is returned as an owned buffer. Otherwise, no copy is performed.
+pub(crate) fn nethuns_pcap_record<'a>(
+    pkthdr: &dyn PkthdrTrait,
+    packet: &'a [u8],
+) -> (nethuns_pcap_pkthdr, Cow<'a, [u8]>) {
+    let vlan_len: u32 = if pkthdr.offvlan_tpid() != 0 { 4 } else { 0 };
+    let bytes = cmp::min(packet.len() as u32, pkthdr.snaplen());
+    
+    let header = nethuns_pcap_pkthdr {
+        ts: nethuns_pcap_timeval {
+            tv_sec: pkthdr.tstamp_sec() as _,
+            tv_usec: pkthdr.tstamp_usec() as _,
+        },
+        caplen: bytes + vlan_len,
+        len: pkthdr.len().saturating_add(vlan_len),
+    };
+    
+    if vlan_len == 0 {
+        return (header, Cow::Borrowed(&packet[..bytes as _]));
+    }
+    
+    // Reinsert the 802.1Q header after the destination and source MAC addresses
+    let split = cmp::min(12, bytes as usize);
+    let mut payload = Vec::with_capacity(header.caplen as _);
+    payload.extend_from_slice(&packet[..split]);
+    payload.extend_from_slice(&pkthdr.offvlan_tpid().to_be_bytes());
+    payload.extend_from_slice(&pkthdr.offvlan_tci().to_be_bytes());
+    payload.extend_from_slice(&packet[split..bytes as _]);
+    
+    (header, Cow::Owned(payload))
+}
+
+
+/// Statistics of the packets read from a pcap file
+/// (see [`NethunsSocketPcap::reader_stats`]).
+#[derive(Clone, Copy, CopyGetters, Debug, Default, PartialEq, Eq)]
+#[getset(get_copy = "pub")]
+pub struct PcapReaderStats {
+    /// Packets read from the file.
+    packets: u64,
+    /// Packets with a wrong IPv4, TCP or UDP checksum
+    /// (counted only if the `verify_checksums` option is set).
+    corrupt: u64,
+}
+
+impl PcapReaderStats {
+    /// Count a packet read from the file, verifying its checksums
+    /// if `verify` is true.
+    fn count(&mut self, packet: &[u8], verify: bool) {
+        self.packets += 1;
+        if verify && !checksums_valid(packet) {
+            self.corrupt += 1;
+        }
+    }
+}
+
+
+/// Verify the IPv4, TCP and UDP checksums of an Ethernet frame.
+///
+/// The frame must have been captured entirely. The checksums which
+/// can't be verified (e.g. unparsable packets, IPv4 fragments)
+/// are considered valid.
+fn checksums_valid(packet: &[u8]) -> bool {
+    let sliced = match SlicedPacket::from_ethernet(packet) {
+        Ok(sliced) => sliced,
+        Err(_) => return true,
+    };
+    
+    // Offset of a subslice of `packet`
+    let offset = |s: &[u8]| s.as_ptr() as usize - packet.as_ptr() as usize;
+    
+    // Drop the Ethernet padding which may follow the IP packet
+    let trim = |ip_end: usize| {
+        let start = offset(sliced.payload);
+        let end = cmp::min(start + sliced.payload.len(), ip_end);
+        &packet[start..cmp::max(start, end)]
+    };
+    
+    match &sliced.ip {
+        Some(InternetSlice::Ipv4(ip, _)) => {
+            let header = ip.to_header();
+            if header.calc_header_checksum().ok() != Some(ip.header_checksum())
+            {
+                return false;
+            }
+            if ip.more_fragments() || ip.fragments_offset() != 0 {
+                return true;
+            }
+            
+            let payload =
+                trim(offset(ip.slice()) + usize::from(ip.total_len()));
+            match &sliced.transport {
+                Some(TransportSlice::Udp(udp)) => {
+                    udp.checksum() == 0
+                        || udp
+                            .to_header()
+                            .calc_checksum_ipv4(&header, payload)
+                            .ok()
+                            == Some(udp.checksum())
+                }
+                Some(TransportSlice::Tcp(tcp)) => {
+                    tcp.to_header()
+                        .calc_checksum_ipv4(&header, payload)
+                        .ok()
+                        == Some(tcp.checksum())
+                }
+                _ => true,
+            }
+        }
+        Some(InternetSlice::Ipv6(ip, _)) => {
+            let header = ip.to_header();
+            let payload = trim(
+                offset(ip.slice()) + 40 + usize::from(ip.payload_length()),
+            );
+            match &sliced.transport {
+                Some(TransportSlice::Udp(udp)) => {
+                    udp.to_header()
+                        .calc_checksum_ipv6(&header, payload)
+                        .ok()
+                        == Some(udp.checksum())
+                }
+                Some(TransportSlice::Tcp(tcp)) => {
+                    tcp.to_header()
+                        .calc_checksum_ipv6(&header, payload)
+                        .ok()
+                        == Some(tcp.checksum())
+                }
+                _ => true,
+            }
+        }
+        None => true,
+    }
+}
+
+
 /// Patched pcap packet header for the Kuznetzov's implementation of TCPDUMP format
 #[allow(non_camel_case_types)]
 #[derive(Clone, Copy, Debug, Default, CopyGetters)]
@@ -278,3 +588,51 @@ pub struct nethuns_pcap_timeval {
     tv_sec: i64,
     tv_usec: i64,
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_nethuns_pcap_pkthdr_new() {
+        let hdr = nethuns_pcap_pkthdr::new(10, 20, 64, 128).unwrap();
+        assert_eq!(hdr.ts().tv_sec(), 10);
+        assert_eq!(hdr.ts().tv_usec(), 20);
+        assert_eq!(hdr.caplen(), 64);
+        assert_eq!(hdr.len(), 128);
+        
+        let hdr = nethuns_pcap_pkthdr::with_nsec(10, 20_000, 64, 64).unwrap();
+        assert_eq!(hdr.ts().tv_usec(), 20);
+        
+        assert!(nethuns_pcap_pkthdr::new(10, 20, 129, 128).is_none());
+    }
+    
+    #[test]
+    fn test_checksums_valid() {
+        let payload = [1_u8, 2, 3, 4];
+        let builder = etherparse::PacketBuilder::ethernet2([1; 6], [2; 6])
+            .ipv4([192, 168, 1, 1], [192, 168, 1, 2], 20)
+            .udp(21, 1234);
+        let mut packet = Vec::with_capacity(builder.size(payload.len()));
+        builder.write(&mut packet, &payload).unwrap();
+        assert!(checksums_valid(&packet));
+        
+        // Ethernet padding is ignored
+        let mut padded = packet.clone();
+        padded.extend_from_slice(&[0; 16]);
+        assert!(checksums_valid(&padded));
+        
+        // Corrupt payload
+        let mut corrupt = packet.clone();
+        *corrupt.last_mut().unwrap() ^= 0xff;
+        assert!(!checksums_valid(&corrupt));
+        
+        let mut stats = PcapReaderStats::default();
+        stats.count(&packet, true);
+        stats.count(&corrupt, true);
+        stats.count(&corrupt, false);
+        assert_eq!(stats.packets(), 3);
+        assert_eq!(stats.corrupt(), 1);
+    }
+}
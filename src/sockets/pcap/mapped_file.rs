@@ -0,0 +1,201 @@
+//! Pcap file read through a memory mapping (`mmap` feature).
+//!
+//! The packets are copied into the ring slots straight from the mapped
+//! pages, so reading a packet doesn't require any system call,
+//! and [`rewind`](super::NethunsSocketPcap::rewind) only moves the cursor.
+
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::os::unix::io::AsRawFd;
+use std::sync::Arc;
+
+use memmap2::{Advice, Mmap};
+
+
+/// File accessed by the built-in pcap reader/writer.
+pub enum PcapFile {
+    /// Regular file opened for reading, mapped in memory.
+    Mapped {
+        /// Mapping of the file, shared with the clones of the reader
+        map: Arc<Mmap>,
+        /// Offset of the cursor from the start of the file
+        pos: usize,
+    },
+    /// File opened for reading which couldn't be mapped
+    /// (e.g. a FIFO or a character device), read through a buffer.
+    Stream(BufReader<File>),
+    /// File opened for writing.
+    File(File),
+}
+
+
+impl PcapFile {
+    /// Open the file `path` for reading, mapping it in memory if possible.
+    ///
+    /// The file must not be modified while it's mapped, otherwise
+    /// the packets read from it could be corrupted. In particular,
+    /// if it's truncated by another process, reading the pages beyond
+    /// the new end of the file raises `SIGBUS`.
+    ///
+    /// # Returns
+    /// * `Ok(PcapFile)` - the opened file.
+    /// * `Err(io::Error)` - if the file couldn't be opened.
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        
+        // Only regular files can be mapped
+        if !file.metadata()?.is_file() {
+            return Ok(Self::Stream(BufReader::new(file)));
+        }
+        
+        // [SAFETY] the mapping is read-only, but the file can still be
+        // modified or truncated by other processes: this is a requirement
+        // for the callers (see the documentation above and the one
+        // of `NethunsSocketPcap::open`)
+        match unsafe { Mmap::map(&file) } {
+            Ok(map) => {
+                // The advice is only a hint: ignore failures
+                let _ = map.advise(Advice::Sequential);
+                Ok(Self::Mapped {
+                    map: Arc::new(map),
+                    pos: 0,
+                })
+            }
+            Err(_) => Ok(Self::Stream(BufReader::new(file))),
+        }
+    }
+    
+    /// Create an independent reader of the same file,
+    /// with the cursor at the start of the file.
+    ///
+    /// The mapping is shared, whereas a stream is opened again
+    /// through `/proc/self/fd`, so that the position is not shared.
+    ///
+    /// # Returns
+    /// * `Ok(PcapFile)` - the new reader.
+    /// * `Err(io::Error)` with kind `Unsupported` - if the file is opened for writing.
+    /// * `Err(io::Error)` - if the file couldn't be opened again.
+    pub fn try_clone_reader(&self) -> io::Result<Self> {
+        match self {
+            Self::Mapped { map, .. } => Ok(Self::Mapped {
+                map: Arc::clone(map),
+                pos: 0,
+            }),
+            Self::Stream(reader) => {
+                let fd = reader.get_ref().as_raw_fd();
+                let file = File::open(format!("/proc/self/fd/{fd}"))?;
+                Ok(Self::Stream(BufReader::new(file)))
+            }
+            Self::File(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the pcap file is opened for writing",
+            )),
+        }
+    }
+}
+
+
+impl From<File> for PcapFile {
+    fn from(file: File) -> Self {
+        Self::File(file)
+    }
+}
+
+
+impl Read for PcapFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Mapped { map, pos } => {
+                let start = (*pos).min(map.len());
+                let n = buf.len().min(map.len() - start);
+                buf[..n].copy_from_slice(&map[start..start + n]);
+                *pos = start + n;
+                Ok(n)
+            }
+            Self::Stream(reader) => reader.read(buf),
+            Self::File(file) => file.read(buf),
+        }
+    }
+}
+
+
+impl Seek for PcapFile {
+    fn seek(&mut self, seek: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::Mapped { map, pos } => {
+                let new_pos = match seek {
+                    SeekFrom::Start(offset) => Some(offset),
+                    SeekFrom::Current(delta) => {
+                        (*pos as u64).checked_add_signed(delta)
+                    }
+                    SeekFrom::End(delta) => {
+                        (map.len() as u64).checked_add_signed(delta)
+                    }
+                }
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "invalid seek to a negative or overflowing position",
+                    )
+                })?;
+                // Positions beyond the end of the file are allowed,
+                // as for `File`: reading from them returns 0 bytes.
+                *pos = usize::try_from(new_pos).unwrap_or(usize::MAX);
+                Ok(new_pos)
+            }
+            Self::Stream(reader) => reader.seek(seek),
+            Self::File(file) => file.seek(seek),
+        }
+    }
+}
+
+
+impl Write for PcapFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::File(file) => file.write(buf),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "the pcap file is opened for reading",
+            )),
+        }
+    }
+    
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::File(file) => file.flush(),
+            _ => Ok(()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_mapped_file_read_seek() {
+        let path = std::env::temp_dir()
+            .join(format!("nethuns-mapped-{}.bin", std::process::id()));
+        std::fs::write(&path, [1_u8, 2, 3, 4, 5]).unwrap();
+        
+        let mut file = PcapFile::open(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(file, PcapFile::Mapped { .. }));
+        
+        let mut buf = [0_u8; 3];
+        assert_eq!(file.read(&mut buf).unwrap(), 3);
+        assert_eq!(buf, [1, 2, 3]);
+        assert_eq!(file.seek(SeekFrom::Current(1)).unwrap(), 4);
+        assert_eq!(file.read(&mut buf).unwrap(), 1);
+        assert_eq!(buf[0], 5);
+        assert_eq!(file.read(&mut buf).unwrap(), 0);
+        
+        assert_eq!(file.seek(SeekFrom::Start(0)).unwrap(), 0);
+        assert_eq!(file.read(&mut buf).unwrap(), 3);
+        assert_eq!(buf, [1, 2, 3]);
+        assert!(file.seek(SeekFrom::Current(-10)).is_err());
+        assert!(file.write(&[0]).is_err());
+    }
+}
@@ -4,9 +4,11 @@
 
 use std::fs::{File, OpenOptions};
 use std::io::prelude::*;
-use std::io::SeekFrom;
+use std::io::{self, SeekFrom};
+#[cfg(not(feature = "mmap"))]
+use std::os::unix::io::AsRawFd;
 use std::sync::atomic::Ordering;
-use std::{cmp, mem};
+use std::{cmp, mem, slice};
 
 use crate::sockets::base::{NethunsSocketBase, RecvPacket};
 use crate::sockets::errors::{
@@ -21,13 +23,26 @@ use super::constants::{
     KUZNETZOV_TCPDUMP_MAGIC, NSEC_TCPDUMP_MAGIC, TCPDUMP_MAGIC,
 };
 use super::{
-    nethuns_pcap_patched_pkthdr, nethuns_pcap_pkthdr, nethuns_pcap_timeval,
-    NethunsSocketPcapInner, NethunsSocketPcapTrait,
+    nethuns_pcap_pkthdr, nethuns_pcap_record, NethunsSocketPcapInner,
+    NethunsSocketPcapTrait,
 };
 
 
 // Define the type of the pcap reader
+#[cfg(not(feature = "mmap"))]
 pub type PcapReaderType = File;
+#[cfg(feature = "mmap")]
+pub type PcapReaderType = super::mapped_file::PcapFile;
+
+
+/// Size of a packet record header in a pcap file
+const PCAP_PKTHDR_LEN: usize = 16;
+/// Size of a packet record header in a pcap file
+/// with the Kuznetzov's format
+const PCAP_PATCHED_PKTHDR_LEN: usize = 24;
+/// Maximum caplen accepted in a packet record header
+/// (same bound used by libpcap and tcpdump).
+const PCAP_MAX_CAPLEN: u32 = 262_144;
 
 
 impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
@@ -40,15 +55,17 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
         Self: Sized,
     {
         let rx_ring = NethunsRing::new(
-            (opt.numblocks * opt.numpackets) as _,
+            opt.rx_ring_slots(),
             opt.packetsize as _,
         );
         
         let snaplen: u32;
+        let file_snaplen: u32;
         let magic: u32;
+        let mut swapped = false;
         
         let reader = if !writing_mode {
-            let mut file = File::open(filename)?;
+            let mut file = open_reader(filename)?;
             let mut file_header = pcap_file_header {
                 magic: 0,
                 version_major: 0,
@@ -62,42 +79,54 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
             // Read PCAP file header
             file.read_exact(any_as_u8_slice_mut(&mut file_header))?;
             
-            // Check if the file format is supported
-            if file_header.magic != TCPDUMP_MAGIC
-                && file_header.magic != KUZNETZOV_TCPDUMP_MAGIC
-                && file_header.magic != NSEC_TCPDUMP_MAGIC
-            {
-                return Err(NethunsPcapOpenError::MagicNotSupported(
-                    file_header.magic,
-                ));
+            // Check if the file format is supported,
+            // either in the byte order of the host or in the opposite one
+            // (e.g. a capture taken on a big-endian machine).
+            if !is_supported_magic(file_header.magic) {
+                if !is_supported_magic(file_header.magic.swap_bytes()) {
+                    return Err(NethunsPcapOpenError::MagicNotSupported(
+                        file_header.magic,
+                    ));
+                }
+                file_header = file_header.swap_bytes();
+                swapped = true;
+            }
+            
+            if opt.strict_snaplen && file_header.snaplen > opt.packetsize {
+                return Err(NethunsPcapOpenError::SnaplenTooLarge {
+                    snaplen: file_header.snaplen,
+                    packetsize: opt.packetsize,
+                });
             }
             
             // Initialize fields for NethunsSocketPcap struct
             snaplen = cmp::min(file_header.snaplen, opt.packetsize);
+            file_snaplen = file_header.snaplen;
             magic = file_header.magic;
             
             file
         } else {
             // Create a new file in pcap format and
             // write the file header according to the TCPDUMP standard.
-            let mut file = OpenOptions::new()
-                .write(true)
-                .truncate(true)
-                .create(true)
-                .open(filename)?;
+            let mut file = create_writer(filename)?;
             
             snaplen = opt.packetsize;
+            file_snaplen = opt.packetsize;
             magic = TCPDUMP_MAGIC;
+            swapped = opt.pcap_byte_order.is_swapped();
             
-            let file_header = pcap_file_header {
+            let mut file_header = pcap_file_header {
                 magic,
                 version_major: 2,
                 version_minor: 4,
                 thiszone: 0,
                 sigfigs: 0,
-                snaplen: 0xffff,
+                snaplen: file_snaplen,
                 linktype: 1, // DLT_EN10MB
             };
+            if swapped {
+                file_header = file_header.swap_bytes();
+            }
             
             file.write_all(any_as_u8_slice(&file_header))?;
             file.flush()?;
@@ -114,7 +143,12 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
             base,
             reader,
             snaplen,
+            file_snaplen,
             magic,
+            swapped,
+            writing_mode,
+            reader_stats: Default::default(),
+            write_error: None,
         })
     }
     
@@ -133,37 +167,74 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
         }
         
         // Read a new packet (header + payload) from the file
-        let mut header = nethuns_pcap_patched_pkthdr::default();
+        let mut raw_header = [0_u8; PCAP_PATCHED_PKTHDR_LEN];
         let header_slice = if self.magic == KUZNETZOV_TCPDUMP_MAGIC {
-            any_as_u8_slice_mut(&mut header)
+            &mut raw_header[..]
         } else {
-            any_as_u8_slice_mut(&mut header.hdr)
+            &mut raw_header[..PCAP_PKTHDR_LEN]
         };
         
-        self.reader.read_exact(header_slice)?;
+        // A clean EOF is only allowed at a record boundary,
+        // i.e. before reading the header of a new packet.
+        match read_record(&mut self.reader, header_slice)? {
+            0 => return Err(NethunsPcapReadError::Eof),
+            n if n < header_slice.len() => {
+                return Err(NethunsPcapReadError::Truncated(
+                    header_slice.len(),
+                    n,
+                ))
+            }
+            _ => {}
+        }
+        let header = PcapRecordHeader::parse(&raw_header, self.swapped);
+        header.validate()?;
         
-        let bytes = cmp::min(caplen, header.hdr.caplen);
+        // Never read more bytes than the ring slot can hold,
+        // whatever the caplen declared in the (untrusted) packet header.
+        let bytes = cmp::min(
+            cmp::min(caplen, header.caplen),
+            slot.packet.len() as u32,
+        );
         
-        self.reader.read_exact(&mut slot.packet[..bytes as _])?;
+        let n =
+            read_record(&mut self.reader, &mut slot.packet[..bytes as _])?;
+        if n < bytes as usize {
+            return Err(NethunsPcapReadError::Truncated(bytes as _, n));
+        }
         
         // Store the information related to the new packet
         // in a free ring slot of the base nethuns socket
-        slot.pkthdr.tstamp_set_sec(header.hdr.ts.tv_sec as _);
+        slot.pkthdr.tstamp_set_sec(header.ts_sec);
         
         if self.magic == NSEC_TCPDUMP_MAGIC {
-            slot.pkthdr.tstamp_set_nsec(header.hdr.ts.tv_usec as _);
+            slot.pkthdr.tstamp_set_nsec(header.ts_frac);
         } else {
-            slot.pkthdr.tstamp_set_usec(header.hdr.ts.tv_usec as _);
+            slot.pkthdr.tstamp_set_usec(header.ts_frac);
         }
         
-        slot.pkthdr.set_len(header.hdr.len);
+        slot.pkthdr.set_len(header.len);
         slot.pkthdr.set_snaplen(bytes);
         
-        if header.hdr.caplen > caplen {
-            let skip = header.hdr.caplen as i64 - caplen as i64;
+        self.reader_stats.count(
+            &slot.packet[..bytes as _],
+            self.base.opt.verify_checksums && bytes == header.len,
+        );
+        
+        // Zero the stale bytes of the slot beyond `caplen`, if required
+        if self.base.opt.zero_slots {
+            slot.packet[bytes as _..].fill(0);
+        }
+        
+        // Skip the bytes of the packet which don't fit in the slot
+        if header.caplen > bytes {
+            let skip = i64::from(header.caplen - bytes);
             self.reader.seek(SeekFrom::Current(skip))?;
         }
         
+        // Pointer to the packet buffer, which is exclusively owned
+        // by the returned packet until it's released.
+        let buf = slot.packet.as_mut_ptr();
+        
         slot.status.store(RingSlotStatus::InUse, Ordering::Release);
         
         rx_ring.rings_mut().advance_head();
@@ -176,7 +247,7 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
             RecvPacket::new(
                 rx_ring.head() as _,
                 &slot.pkthdr,
-                &slot.packet[..bytes as _],
+                unsafe { slice::from_raw_parts_mut(buf, bytes as _) },
                 &slot.status,
             )
         };
@@ -191,7 +262,8 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
         packet: &[u8],
     ) -> Result<usize, NethunsPcapWriteError> {
         // Write the header + packet into the file
-        self.reader.write_all(any_as_u8_slice(header))?;
+        self.reader
+            .write_all(&PcapRecordHeader::encode(header, self.swapped))?;
         self.reader.write_all(packet)?;
         self.reader.flush()?;
         Ok(packet.len())
@@ -203,40 +275,22 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
         pkthdr: &dyn PkthdrTrait,
         packet: &[u8],
     ) -> Result<u32, NethunsPcapStoreError> {
-        // Build a packet header for the pcap format from the
-        // header of the original packet
-        let has_vlan_offload = pkthdr.offvlan_tpid();
-        let header = nethuns_pcap_pkthdr {
-            ts: nethuns_pcap_timeval {
-                tv_sec: pkthdr.tstamp_sec() as _,
-                tv_usec: pkthdr.tstamp_usec() as _,
-            },
-            caplen: cmp::min(
-                packet.len() as _,
-                pkthdr.snaplen() + 4 * has_vlan_offload as u32,
-            ),
-            len: pkthdr.len() + 4 * has_vlan_offload as u32,
+        // Truncate the packet to the snaplen of the socket, if any
+        let packet = match self.base.opt.snaplen {
+            Some(snaplen) => &packet[..cmp::min(packet.len(), snaplen as _)],
+            None => packet,
         };
         
-        // Write the packet header
-        self.reader.write_all(any_as_u8_slice(&header))?;
+        // Build a pcap record from the header of the original packet
+        let (header, payload) = nethuns_pcap_record(pkthdr, packet);
         
-        let mut clen: u32 = header.caplen;
-        
-        // Write the packet payload
-        if has_vlan_offload != 0 {
-            let h8021q: [u16; 2] =
-                [pkthdr.offvlan_tpid().to_be(), pkthdr.offvlan_tci().to_be()];
-            self.reader.write_all(&packet[..12])?;
-            self.reader.write_all(any_as_u8_slice(&h8021q))?;
-            clen = header.caplen - 16;
-            self.reader.write_all(&packet[12..(clen + 12) as _])?;
-        } else {
-            self.reader.write_all(&packet[..header.caplen as _])?;
-        }
+        // Write the packet header and payload
+        self.reader
+            .write_all(&PcapRecordHeader::encode(&header, self.swapped))?;
+        self.reader.write_all(&payload)?;
         
         self.reader.flush()?;
-        Ok(clen)
+        Ok(header.caplen)
     }
     
     
@@ -246,6 +300,197 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
             .seek(SeekFrom::Start(mem::size_of::<pcap_file_header>() as _))
             .map_err(NethunsPcapRewindError::from)
     }
+    
+    
+    fn try_clone(&self) -> io::Result<Self> {
+        if self.writing_mode {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "[try_clone] a pcap file opened for writing can't be cloned",
+            ));
+        }
+        
+        let mut reader = reopen_reader(&self.reader)?;
+        reader
+            .seek(SeekFrom::Start(mem::size_of::<pcap_file_header>() as _))?;
+        
+        let opt = self.base.opt.clone();
+        let rx_ring = NethunsRing::new(
+            opt.rx_ring_slots(),
+            opt.packetsize as _,
+        );
+        let base = NethunsSocketBase {
+            opt,
+            rx_ring: Some(rx_ring),
+            ..Default::default()
+        };
+        
+        Ok(NethunsSocketPcapInner {
+            base,
+            reader,
+            snaplen: self.snaplen,
+            file_snaplen: self.file_snaplen,
+            magic: self.magic,
+            swapped: self.swapped,
+            writing_mode: false,
+            reader_stats: Default::default(),
+            write_error: None,
+        })
+    }
+}
+
+
+/// Open the pcap file `filename` for reading.
+#[cfg(not(feature = "mmap"))]
+fn open_reader(filename: &str) -> io::Result<PcapReaderType> {
+    File::open(filename)
+}
+
+/// Open the pcap file `filename` for reading, mapping it in memory.
+#[cfg(feature = "mmap")]
+fn open_reader(filename: &str) -> io::Result<PcapReaderType> {
+    PcapReaderType::open(filename)
+}
+
+
+/// Open again the file read by `reader`, with an independent position.
+///
+/// Unlike [`File::try_clone`], whose descriptor shares the position with
+/// the original one, the file is opened again through `/proc/self/fd`.
+#[cfg(not(feature = "mmap"))]
+fn reopen_reader(reader: &PcapReaderType) -> io::Result<PcapReaderType> {
+    File::open(format!("/proc/self/fd/{}", reader.as_raw_fd()))
+}
+
+/// Open again the file read by `reader`, with an independent position.
+#[cfg(feature = "mmap")]
+fn reopen_reader(reader: &PcapReaderType) -> io::Result<PcapReaderType> {
+    reader.try_clone_reader()
+}
+
+
+/// Create the pcap file `filename` for writing, truncating it if it exists.
+fn create_writer(filename: &str) -> io::Result<PcapReaderType> {
+    let file = OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .create(true)
+        .open(filename)?;
+    #[cfg(feature = "mmap")]
+    let file = PcapReaderType::from(file);
+    Ok(file)
+}
+
+
+/// Read from `reader` until `buf` is full or the end of file is reached.
+///
+/// Unlike [`Read::read_exact`], this function allows the caller to
+/// distinguish between a clean end of file (no bytes read) and
+/// a truncated record (some bytes read).
+///
+/// # Returns
+/// * `Ok(usize)` - the number of bytes read, which is less than `buf.len()` only if the end of file has been reached.
+/// * `Err(NethunsPcapReadError::FileError)` - if an I/O error occurs while reading the file.
+fn read_record<R: Read>(
+    reader: &mut R,
+    buf: &mut [u8],
+) -> Result<usize, NethunsPcapReadError> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(n) => total += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(NethunsPcapReadError::FileError(e)),
+        }
+    }
+    Ok(total)
+}
+
+
+/// Check if `magic` identifies a pcap format supported by the reader.
+fn is_supported_magic(magic: u32) -> bool {
+    magic == TCPDUMP_MAGIC
+        || magic == KUZNETZOV_TCPDUMP_MAGIC
+        || magic == NSEC_TCPDUMP_MAGIC
+}
+
+
+/// Fields of a packet record header, as stored in a pcap file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PcapRecordHeader {
+    ts_sec: u32,
+    /// Microseconds or nanoseconds, depending on the magic of the file
+    ts_frac: u32,
+    caplen: u32,
+    len: u32,
+}
+
+impl PcapRecordHeader {
+    /// Parse the header of a packet record, byte-swapping its fields
+    /// if the file has the opposite byte order of the host.
+    /// The additional fields of the Kuznetzov's format are ignored.
+    fn parse(raw: &[u8], swapped: bool) -> Self {
+        let field = |i: usize| {
+            let bytes = raw[i * 4..(i + 1) * 4]
+                .try_into()
+                .expect("the slice should be 4 bytes long");
+            let value = u32::from_ne_bytes(bytes);
+            if swapped {
+                value.swap_bytes()
+            } else {
+                value
+            }
+        };
+        Self {
+            ts_sec: field(0),
+            ts_frac: field(1),
+            caplen: field(2),
+            len: field(3),
+        }
+    }
+    
+    /// Check that the lengths declared in the header are consistent,
+    /// so that a corrupted record isn't read as a (huge) packet.
+    ///
+    /// # Returns
+    /// * `Ok(())` - if the header is valid.
+    /// * `Err(NethunsPcapReadError::MalformedRecord)` - if `caplen` exceeds the original length of the packet or [`PCAP_MAX_CAPLEN`].
+    fn validate(&self) -> Result<(), NethunsPcapReadError> {
+        if self.caplen > self.len {
+            return Err(NethunsPcapReadError::MalformedRecord(format!(
+                "caplen ({}) greater than packet length ({})",
+                self.caplen, self.len
+            )));
+        }
+        if self.caplen > PCAP_MAX_CAPLEN {
+            return Err(NethunsPcapReadError::MalformedRecord(format!(
+                "caplen ({}) greater than the maximum ({PCAP_MAX_CAPLEN})",
+                self.caplen
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Encode a packet header as a packet record header,
+    /// in the opposite byte order of the host if `swapped` is true.
+    fn encode(
+        header: &nethuns_pcap_pkthdr,
+        swapped: bool,
+    ) -> [u8; PCAP_PKTHDR_LEN] {
+        let mut raw = [0_u8; PCAP_PKTHDR_LEN];
+        let fields = [
+            header.ts.tv_sec as u32,
+            header.ts.tv_usec as u32,
+            header.caplen,
+            header.len,
+        ];
+        for (i, value) in fields.iter().enumerate() {
+            let value = if swapped { value.swap_bytes() } else { *value };
+            raw[i * 4..(i + 1) * 4].copy_from_slice(&value.to_ne_bytes());
+        }
+        raw
+    }
 }
 
 
@@ -293,3 +538,269 @@ struct pcap_file_header {
     snaplen: u32,
     linktype: u32,
 }
+
+impl pcap_file_header {
+    /// Reverse the byte order of all the fields of the header.
+    fn swap_bytes(self) -> Self {
+        Self {
+            magic: self.magic.swap_bytes(),
+            version_major: self.version_major.swap_bytes(),
+            version_minor: self.version_minor.swap_bytes(),
+            thiszone: self.thiszone.swap_bytes(),
+            sigfigs: self.sigfigs.swap_bytes(),
+            snaplen: self.snaplen.swap_bytes(),
+            linktype: self.linktype.swap_bytes(),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    
+    use crate::types::PcapByteOrder;
+    
+    use super::*;
+    
+    /// Temporary pcap file, deleted when dropped.
+    struct TempPcap(PathBuf);
+    
+    impl TempPcap {
+        fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!(
+                "nethuns-{name}-{}.pcap",
+                std::process::id()
+            )))
+        }
+        
+        fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+    
+    impl Drop for TempPcap {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+    
+    /// Options of the sockets opened by the tests.
+    fn test_options() -> NethunsSocketOptions {
+        NethunsSocketOptions {
+            numblocks: 1,
+            numpackets: 4,
+            packetsize: 64,
+            ..Default::default()
+        }
+    }
+    
+    #[test]
+    fn test_read_record() {
+        let data = [1_u8, 2, 3, 4, 5];
+        let mut reader = &data[..];
+        
+        // Full record
+        let mut buf = [0_u8; 3];
+        assert_eq!(read_record(&mut reader, &mut buf).unwrap(), 3);
+        assert_eq!(buf, [1, 2, 3]);
+        
+        // Truncated record
+        let mut buf = [0_u8; 3];
+        assert_eq!(read_record(&mut reader, &mut buf).unwrap(), 2);
+        assert_eq!(buf[..2], [4, 5]);
+        
+        // Clean end of file
+        let mut buf = [0_u8; 3];
+        assert_eq!(read_record(&mut reader, &mut buf).unwrap(), 0);
+    }
+    
+    #[test]
+    fn test_read_swapped_endian() {
+        // Big-endian file header (if the host is little-endian,
+        // and vice versa) followed by a single packet record
+        let mut data = Vec::new();
+        data.extend_from_slice(&TCPDUMP_MAGIC.swap_bytes().to_ne_bytes());
+        data.extend_from_slice(&2_u16.swap_bytes().to_ne_bytes());
+        data.extend_from_slice(&4_u16.swap_bytes().to_ne_bytes());
+        for field in [0_u32, 0, 0xffff, 1] {
+            data.extend_from_slice(&field.swap_bytes().to_ne_bytes());
+        }
+        for field in [1_700_000_000_u32, 123_456, 4, 60] {
+            data.extend_from_slice(&field.swap_bytes().to_ne_bytes());
+        }
+        data.extend_from_slice(&[0xde, 0xad, 0xbe, 0xef]);
+        
+        let file = TempPcap::new("swapped");
+        std::fs::write(file.path(), &data).unwrap();
+        
+        let mut socket =
+            NethunsSocketPcapInner::open(test_options(), file.path(), false)
+                .unwrap();
+        assert!(socket.swapped);
+        assert_eq!(socket.magic, TCPDUMP_MAGIC);
+        
+        let packet = socket.read().unwrap();
+        assert_eq!(packet.pkthdr().tstamp_sec(), 1_700_000_000);
+        assert_eq!(packet.pkthdr().tstamp_usec(), 123_456);
+        assert_eq!(packet.pkthdr().snaplen(), 4);
+        assert_eq!(packet.pkthdr().len(), 60);
+        assert_eq!(packet.buffer(), &[0xde, 0xad, 0xbe, 0xef]);
+        drop(packet);
+        
+        assert!(matches!(socket.read(), Err(NethunsPcapReadError::Eof)));
+    }
+    
+    #[test]
+    fn test_write_big_endian() {
+        let file = TempPcap::new("big-endian");
+        let opt = NethunsSocketOptions {
+            pcap_byte_order: PcapByteOrder::BigEndian,
+            ..test_options()
+        };
+        
+        let mut writer =
+            NethunsSocketPcapInner::open(opt.clone(), file.path(), true)
+                .unwrap();
+        let header =
+            nethuns_pcap_pkthdr::new(1_700_000_000, 123_456, 4, 60).unwrap();
+        writer.write(&header, &[0xde, 0xad, 0xbe, 0xef]).unwrap();
+        drop(writer);
+        
+        let data = std::fs::read(file.path()).unwrap();
+        assert_eq!(data[..4], TCPDUMP_MAGIC.to_be_bytes());
+        assert_eq!(data[24..28], 1_700_000_000_u32.to_be_bytes());
+        
+        let mut reader =
+            NethunsSocketPcapInner::open(opt, file.path(), false).unwrap();
+        assert_eq!(reader.swapped, cfg!(target_endian = "little"));
+        
+        let packet = reader.read().unwrap();
+        assert_eq!(packet.pkthdr().tstamp_sec(), 1_700_000_000);
+        assert_eq!(packet.pkthdr().tstamp_usec(), 123_456);
+        assert_eq!(packet.pkthdr().len(), 60);
+        assert_eq!(packet.buffer(), &[0xde, 0xad, 0xbe, 0xef]);
+    }
+    
+    #[test]
+    fn test_try_clone() {
+        let file = TempPcap::new("try-clone");
+        
+        let mut writer =
+            NethunsSocketPcapInner::open(test_options(), file.path(), true)
+                .unwrap();
+        for i in 0..2_u8 {
+            let header =
+                nethuns_pcap_pkthdr::new(1_700_000_000, 0, 1, 1).unwrap();
+            writer.write(&header, &[i]).unwrap();
+        }
+        assert!(writer.try_clone().is_err());
+        drop(writer);
+        
+        let mut reader =
+            NethunsSocketPcapInner::open(test_options(), file.path(), false)
+                .unwrap();
+        // The clone doesn't need the file to exist under its name
+        std::fs::remove_file(file.path()).unwrap();
+        assert_eq!(reader.read().unwrap().buffer(), &[0]);
+        
+        // The clone starts from the first packet...
+        let mut clone = reader.try_clone().unwrap();
+        assert_eq!(clone.read().unwrap().buffer(), &[0]);
+        assert_eq!(clone.read().unwrap().buffer(), &[1]);
+        assert!(matches!(clone.read(), Err(NethunsPcapReadError::Eof)));
+        
+        // ...without moving the position of the original reader
+        assert_eq!(reader.read().unwrap().buffer(), &[1]);
+        assert!(matches!(reader.read(), Err(NethunsPcapReadError::Eof)));
+    }
+    
+    #[test]
+    fn test_strict_snaplen() {
+        let file = TempPcap::new("strict");
+        let strict = NethunsSocketOptions {
+            strict_snaplen: true,
+            ..test_options()
+        };
+        
+        // The file header declares the packetsize of the writer
+        let writer = NethunsSocketPcapInner::open(
+            NethunsSocketOptions {
+                packetsize: 128,
+                ..test_options()
+            },
+            file.path(),
+            true,
+        )
+        .unwrap();
+        assert_eq!(writer.file_snaplen, 128);
+        drop(writer);
+        
+        let reader =
+            NethunsSocketPcapInner::open(test_options(), file.path(), false)
+                .unwrap();
+        assert_eq!(reader.file_snaplen, 128);
+        assert_eq!(reader.snaplen, 64);
+        
+        let result =
+            NethunsSocketPcapInner::open(strict.clone(), file.path(), false);
+        assert!(matches!(
+            result,
+            Err(NethunsPcapOpenError::SnaplenTooLarge {
+                snaplen: 128,
+                packetsize: 64,
+            })
+        ));
+        
+        // A file written with the same packetsize is accepted
+        drop(
+            NethunsSocketPcapInner::open(test_options(), file.path(), true)
+                .unwrap(),
+        );
+        assert!(
+            NethunsSocketPcapInner::open(strict, file.path(), false).is_ok()
+        );
+    }
+    
+    #[test]
+    fn test_read_malformed_record() {
+        // (caplen, len) of records with inconsistent lengths
+        for (i, (caplen, len)) in
+            [(8_u32, 4_u32), (PCAP_MAX_CAPLEN + 1, u32::MAX)]
+                .into_iter()
+                .enumerate()
+        {
+            let mut data = Vec::new();
+            data.extend_from_slice(&TCPDUMP_MAGIC.to_ne_bytes());
+            data.extend_from_slice(&2_u16.to_ne_bytes());
+            data.extend_from_slice(&4_u16.to_ne_bytes());
+            for field in [0_u32, 0, 0xffff, 1, 0, 0, caplen, len] {
+                data.extend_from_slice(&field.to_ne_bytes());
+            }
+            data.extend_from_slice(&[0xaa; 8]);
+            
+            let file = TempPcap::new(&format!("malformed-{i}"));
+            std::fs::write(file.path(), &data).unwrap();
+            
+            let mut socket =
+                NethunsSocketPcapInner::open(test_options(), file.path(), false)
+                    .unwrap();
+            
+            assert!(matches!(
+                socket.read(),
+                Err(NethunsPcapReadError::MalformedRecord(_))
+            ));
+        }
+    }
+    
+    #[test]
+    fn test_validate_zero_caplen() {
+        let header = PcapRecordHeader {
+            ts_sec: 0,
+            ts_frac: 0,
+            caplen: 0,
+            len: 0,
+        };
+        assert!(header.validate().is_ok());
+    }
+}
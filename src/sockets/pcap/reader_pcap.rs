@@ -2,12 +2,13 @@
 //! when the default pcap reader is requested
 //! (i.e. `NETHUNS_USE_BUILTIN_PCAP_READER` feature is **not** enabled).
 
-use std::cmp;
 use std::fs::File;
+use std::os::fd::{AsRawFd, RawFd};
 use std::sync::atomic;
+use std::{cmp, io, slice};
 
 use pcap_parser::traits::PcapReaderIterator;
-use pcap_parser::{LegacyPcapReader, PcapBlockOwned, PcapError};
+use pcap_parser::{LegacyPcapReader, PcapBlockOwned, PcapError, PcapHeader};
 
 use crate::sockets::base::{NethunsSocketBase, RecvPacket};
 use crate::sockets::errors::{
@@ -24,8 +25,15 @@ use super::{
 };
 
 
-// Define the type of the pcap reader
-pub type PcapReaderType = LegacyPcapReader<File>;
+/// Reader of the pcap file.
+///
+/// The raw descriptor of the file is kept alongside the reader,
+/// which owns it, so that the file can be opened again
+/// by [`NethunsSocketPcapTrait::try_clone`].
+pub struct PcapReaderType {
+    reader: LegacyPcapReader<File>,
+    fd: RawFd,
+}
 
 
 impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
@@ -41,7 +49,7 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
         let snaplen = opt.packetsize;
         
         let rx_ring = NethunsRing::new(
-            (opt.numblocks * opt.numpackets) as _,
+            opt.rx_ring_slots(),
             opt.packetsize as _,
         );
         let base = NethunsSocketBase {
@@ -50,25 +58,26 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
             ..Default::default()
         };
         
-        let mut reader = LegacyPcapReader::new(65536, File::open(filename)?)?;
-        let header = match reader.next() {
-            Ok((offset, block)) => match block {
-                PcapBlockOwned::LegacyHeader(header) => {
-                    let header = header.clone();
-                    reader.consume(offset);
-                    header
-                }
-                // The first read block should be the header of the pcap file
-                _ => unreachable!(),
-            },
-            Err(e) => return Err(NethunsPcapOpenError::from(e)),
-        };
+        let (reader, header) = open_reader(File::open(filename)?)?;
+        
+        if base.opt.strict_snaplen && header.snaplen > base.opt.packetsize {
+            return Err(NethunsPcapOpenError::SnaplenTooLarge {
+                snaplen: header.snaplen,
+                packetsize: base.opt.packetsize,
+            });
+        }
         
         Ok(NethunsSocketPcapInner {
             base,
             reader,
             snaplen,
+            file_snaplen: header.snaplen,
             magic: header.magic_number,
+            // byte order is handled by `pcap_parser`
+            swapped: false,
+            writing_mode: false,
+            reader_stats: Default::default(),
+            write_error: None,
         })
     }
     
@@ -89,10 +98,13 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
         
         let bytes: u32;
         loop {
-            match self.reader.next() {
+            match self.reader.reader.next() {
                 Ok((offset, block)) => match block {
                     PcapBlockOwned::Legacy(packet) => {
-                        bytes = cmp::min(caplen, packet.caplen);
+                        bytes = cmp::min(
+                            cmp::min(caplen, packet.data.len() as u32),
+                            slot.packet.len() as u32,
+                        );
                         
                         slot.pkthdr.tstamp_set_sec(packet.ts_sec);
                         
@@ -105,21 +117,36 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
                         slot.pkthdr.set_len(packet.origlen);
                         slot.pkthdr.set_snaplen(bytes);
                         
-                        slot.packet.copy_from_slice(&packet.data[..bytes as _]);
-                        self.reader.consume(offset);
+                        slot.packet[..bytes as _]
+                            .copy_from_slice(&packet.data[..bytes as _]);
+                        self.reader_stats.count(
+                            &slot.packet[..bytes as _],
+                            self.base.opt.verify_checksums
+                                && bytes == packet.origlen,
+                        );
+                        self.reader.reader.consume(offset);
                         break;
                     }
                     // We should have read a packet
                     _ => unreachable!(),
                 },
                 Err(PcapError::Incomplete) => {
-                    self.reader.refill()?;
+                    self.reader.reader.refill()?;
                     continue;
                 }
                 Err(e) => return Err(NethunsPcapReadError::from(e)),
             };
         }
         
+        // Zero the stale bytes of the slot beyond `caplen`, if required
+        if self.base.opt.zero_slots {
+            slot.packet[bytes as _..].fill(0);
+        }
+        
+        // Pointer to the packet buffer, which is exclusively owned
+        // by the returned packet until it's released.
+        let buf = slot.packet.as_mut_ptr();
+        
         slot.status
             .store(RingSlotStatus::InUse, atomic::Ordering::Release);
         
@@ -133,7 +160,7 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
             RecvPacket::new(
                 rx_ring.head() as _,
                 &slot.pkthdr,
-                &slot.packet[..bytes as _],
+                unsafe { slice::from_raw_parts_mut(buf, bytes as _) },
                 &slot.status,
             )
         };
@@ -163,4 +190,68 @@ impl NethunsSocketPcapTrait for NethunsSocketPcapInner {
     fn rewind(&mut self) -> Result<u64, NethunsPcapRewindError> {
         Err(NethunsPcapRewindError::NotSupported)
     }
+    
+    
+    fn try_clone(&self) -> io::Result<Self> {
+        // Unlike `File::try_clone`, whose descriptor shares the position
+        // with the original one, the file is opened again
+        // through `/proc/self/fd`.
+        let file = File::open(format!("/proc/self/fd/{}", self.reader.fd))?;
+        let (reader, _) = open_reader(file).map_err(|e| match e {
+            NethunsPcapOpenError::FileError(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e.to_string()),
+        })?;
+        
+        let opt = self.base.opt.clone();
+        let rx_ring = NethunsRing::new(
+            opt.rx_ring_slots(),
+            opt.packetsize as _,
+        );
+        let base = NethunsSocketBase {
+            opt,
+            rx_ring: Some(rx_ring),
+            ..Default::default()
+        };
+        
+        Ok(NethunsSocketPcapInner {
+            base,
+            reader,
+            snaplen: self.snaplen,
+            file_snaplen: self.file_snaplen,
+            magic: self.magic,
+            swapped: self.swapped,
+            writing_mode: false,
+            reader_stats: Default::default(),
+            write_error: None,
+        })
+    }
+}
+
+
+/// Create a reader of the pcap `file` and parse its header,
+/// leaving the reader positioned at the first packet.
+///
+/// # Returns
+/// * `Ok((PcapReaderType, PcapHeader))` - the reader and the file header.
+/// * `Err(NethunsPcapOpenError::PcapError)` - if the file header couldn't be parsed.
+/// * `Err(NethunsPcapOpenError::FileError)` - if an I/O error occurs while reading the file.
+fn open_reader(
+    file: File,
+) -> Result<(PcapReaderType, PcapHeader), NethunsPcapOpenError> {
+    let fd = file.as_raw_fd();
+    let mut reader = LegacyPcapReader::new(65536, file)?;
+    let header = match reader.next() {
+        Ok((offset, block)) => match block {
+            PcapBlockOwned::LegacyHeader(header) => {
+                let header = header.clone();
+                reader.consume(offset);
+                header
+            }
+            // The first read block should be the header of the pcap file
+            _ => unreachable!(),
+        },
+        Err(e) => return Err(NethunsPcapOpenError::from(e)),
+    };
+    
+    Ok((PcapReaderType { reader, fd }, header))
 }
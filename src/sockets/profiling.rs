@@ -0,0 +1,216 @@
+//! Timing of the hot paths of the sockets (`profiling` feature).
+//!
+//! When the feature is enabled, the duration of each operation
+//! (see `TimedOp`) is recorded into thread-local histograms,
+//! which can be read with [`NethunsSocket::timing_report`](crate::sockets::NethunsSocket::timing_report).
+//! When the feature is disabled, the instrumentation is compiled out.
+
+
+/// Evaluate `$body`, recording its duration for the operation
+/// `TimedOp::$op` in the histograms of the current thread.
+#[cfg(feature = "profiling")]
+macro_rules! nethuns_timed {
+    ($op: ident, $body: expr) => {{
+        let start = ::std::time::Instant::now();
+        let result = $body;
+        $crate::sockets::profiling::record(
+            $crate::sockets::profiling::TimedOp::$op,
+            start.elapsed(),
+        );
+        result
+    }};
+}
+
+/// Evaluate `$body` (the `profiling` feature is disabled).
+#[cfg(not(feature = "profiling"))]
+macro_rules! nethuns_timed {
+    ($op: ident, $body: expr) => {
+        $body
+    };
+}
+pub(crate) use nethuns_timed;
+
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "profiling")] {
+        use std::cell::RefCell;
+        use std::time::Duration;
+        
+        use getset::CopyGetters;
+        
+        
+        /// Number of buckets of a [`TimingHistogram`].
+        const NUM_BUCKETS: usize = 32;
+        
+        
+        /// Operation timed by the `profiling` feature.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum TimedOp {
+            /// Whole [`NethunsSocket::recv`](crate::sockets::NethunsSocket::recv) call.
+            Recv,
+            /// Call of the filter closure, inside `recv`.
+            Filter,
+            /// Whole [`NethunsSocket::send`](crate::sockets::NethunsSocket::send) call,
+            /// including the copy of the packet into the ring.
+            Send,
+            /// Whole [`NethunsSocket::flush`](crate::sockets::NethunsSocket::flush) call.
+            Flush,
+            /// System call which synchronizes the rings with the kernel
+            /// (e.g. `ioctl(NIOCRXSYNC)`), inside `recv` and `flush`.
+            Sync,
+        }
+        
+        
+        /// Histogram of the durations of an operation.
+        ///
+        /// The bucket `i` counts the durations in the range
+        /// `[2^i, 2^(i+1))` nanoseconds (the first one also counts 0 ns,
+        /// the last one all the longer durations).
+        #[derive(Clone, CopyGetters, Debug, Default, PartialEq, Eq)]
+        pub struct TimingHistogram {
+            /// Number of timed calls.
+            #[getset(get_copy = "pub")]
+            count: u64,
+            /// Total time spent in the timed calls.
+            #[getset(get_copy = "pub")]
+            total: Duration,
+            /// Shortest timed call.
+            #[getset(get_copy = "pub")]
+            min: Duration,
+            /// Longest timed call.
+            #[getset(get_copy = "pub")]
+            max: Duration,
+            buckets: [u64; NUM_BUCKETS],
+        }
+        
+        impl TimingHistogram {
+            /// Get the buckets of the histogram.
+            #[inline(always)]
+            pub fn buckets(&self) -> &[u64] {
+                &self.buckets
+            }
+            
+            /// Get the mean duration of the timed calls,
+            /// or `None` if no call has been timed.
+            pub fn mean(&self) -> Option<Duration> {
+                (self.count > 0).then(|| {
+                    self.total / u32::try_from(self.count).unwrap_or(u32::MAX)
+                })
+            }
+            
+            /// Get an upper bound of the `q`-quantile (`0.0..=1.0`)
+            /// of the durations, with the resolution of the buckets,
+            /// or `None` if no call has been timed.
+            pub fn quantile(&self, q: f64) -> Option<Duration> {
+                if self.count == 0 {
+                    return None;
+                }
+                let target = (q.clamp(0.0, 1.0) * self.count as f64).ceil();
+                let mut seen = 0;
+                for (i, &n) in self.buckets.iter().enumerate() {
+                    seen += n;
+                    if seen as f64 >= target.max(1.0) {
+                        let bound = Duration::from_nanos(1 << (i + 1));
+                        return Some(bound.min(self.max));
+                    }
+                }
+                Some(self.max)
+            }
+            
+            fn record(&mut self, elapsed: Duration) {
+                let nanos =
+                    u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX);
+                let bucket = (63 - nanos.max(1).leading_zeros()) as usize;
+                self.buckets[bucket.min(NUM_BUCKETS - 1)] += 1;
+                self.count += 1;
+                self.total += elapsed;
+                if self.count == 1 || elapsed < self.min {
+                    self.min = elapsed;
+                }
+                self.max = self.max.max(elapsed);
+            }
+        }
+        
+        
+        /// Timings of the hot paths of the sockets used by a thread.
+        #[derive(Clone, Debug, Default, PartialEq, Eq)]
+        pub struct TimingReport {
+            recv: TimingHistogram,
+            filter: TimingHistogram,
+            send: TimingHistogram,
+            flush: TimingHistogram,
+            sync: TimingHistogram,
+        }
+        
+        impl TimingReport {
+            /// Get the histogram of the durations of the operation `op`.
+            pub fn get(&self, op: TimedOp) -> &TimingHistogram {
+                match op {
+                    TimedOp::Recv => &self.recv,
+                    TimedOp::Filter => &self.filter,
+                    TimedOp::Send => &self.send,
+                    TimedOp::Flush => &self.flush,
+                    TimedOp::Sync => &self.sync,
+                }
+            }
+            
+            fn get_mut(&mut self, op: TimedOp) -> &mut TimingHistogram {
+                match op {
+                    TimedOp::Recv => &mut self.recv,
+                    TimedOp::Filter => &mut self.filter,
+                    TimedOp::Send => &mut self.send,
+                    TimedOp::Flush => &mut self.flush,
+                    TimedOp::Sync => &mut self.sync,
+                }
+            }
+        }
+        
+        
+        thread_local! {
+            static TIMINGS: RefCell<TimingReport> =
+                RefCell::new(TimingReport::default());
+        }
+        
+        /// Record the duration of an operation in the histograms
+        /// of the current thread.
+        #[inline(always)]
+        pub(crate) fn record(op: TimedOp, elapsed: Duration) {
+            TIMINGS.with(|t| t.borrow_mut().get_mut(op).record(elapsed));
+        }
+        
+        /// Get a copy of the timings of the current thread.
+        pub(crate) fn report() -> TimingReport {
+            TIMINGS.with(|t| t.borrow().clone())
+        }
+        
+        /// Reset the timings of the current thread.
+        pub(crate) fn reset() {
+            TIMINGS.with(|t| *t.borrow_mut() = TimingReport::default());
+        }
+        
+        
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            
+            #[test]
+            fn test_timing_histogram() {
+                let mut h = TimingHistogram::default();
+                assert_eq!(h.mean(), None);
+                
+                h.record(Duration::from_nanos(0));
+                h.record(Duration::from_nanos(100));
+                h.record(Duration::from_nanos(3000));
+                assert_eq!(h.count(), 3);
+                assert_eq!(h.min(), Duration::ZERO);
+                assert_eq!(h.max(), Duration::from_nanos(3000));
+                assert_eq!(h.mean(), Some(Duration::from_nanos(1033)));
+                assert_eq!(h.buckets()[0], 1);
+                assert_eq!(h.buckets()[6], 1); // 64..128 ns
+                assert_eq!(h.buckets()[11], 1); // 2048..4096 ns
+                assert_eq!(h.quantile(0.5), Some(Duration::from_nanos(128)));
+                assert_eq!(h.quantile(1.0), Some(Duration::from_nanos(3000)));
+            }
+        }
+    }
+}
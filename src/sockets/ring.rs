@@ -4,7 +4,7 @@ use core::fmt;
 use std::cmp;
 use std::sync::atomic::{AtomicU8, Ordering};
 
-use getset::{Getters, MutGetters};
+use getset::{CopyGetters, Getters, MutGetters};
 
 use super::api::Pkthdr;
 
@@ -83,6 +83,74 @@ impl NethunsRing {
         self.rings.tail()
     }
     
+    /// Get the number of times the head index has wrapped around
+    /// the end of the ring, back to the first slot.
+    #[inline(always)]
+    pub fn wrap_count(&self) -> u64 {
+        (self.head() / self.size()) as u64
+    }
+    
+    
+    /// Take a snapshot of the indexes of the ring
+    /// and of the status of its slots.
+    pub fn dump(&self) -> RingDump {
+        RingDump {
+            size: self.size(),
+            head: self.head(),
+            tail: self.tail(),
+            slots: (0..self.size())
+                .map(|i| self.get_slot(i).status.load(Ordering::Acquire))
+                .collect(),
+        }
+    }
+    
+    
+    /// Check the consistency of the indexes and of the slot statuses
+    /// of the ring (debug builds only), to catch desync bugs
+    /// close to where they happen.
+    ///
+    /// The slots between the consumer and the producer index must be
+    /// at most as many as the slots of the ring. In an RX ring, the slots
+    /// past the head must be `Free`, since they haven't been given
+    /// to the application yet.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the ring is consistent.
+    /// * `Err(String)` - The description of the first violated invariant.
+    #[cfg(debug_assertions)]
+    pub fn check_invariants(&self, kind: RingKind) -> Result<(), String> {
+        // RX: `recv` advances the head and the release of the slots
+        // advances the tail. TX: `send` advances the tail and `flush`
+        // advances the head.
+        let (first, last) = match kind {
+            RingKind::Rx => (self.tail(), self.head()),
+            RingKind::Tx => (self.head(), self.tail()),
+        };
+        let used = last.wrapping_sub(first);
+        if used > self.size() {
+            return Err(format!(
+                "{kind:?} ring: head {} and tail {} are {used} slots apart, but the ring has {} slots",
+                self.head(),
+                self.tail(),
+                self.size()
+            ));
+        }
+        
+        if kind == RingKind::Rx {
+            for i in (used..self.size()).map(|k| first.wrapping_add(k)) {
+                let status = self.get_slot(i).status.load(Ordering::Acquire);
+                if status != RingSlotStatus::Free {
+                    return Err(format!(
+                        "Rx ring: slot {} is {status:?}, but it's past the head {}",
+                        i % self.size(),
+                        self.head()
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+    
     
     /// Get the number of the consecutive available slots
     /// in the ring, starting from the given position.
@@ -161,8 +229,64 @@ impl NethunsRingSlot {
 }
 
 
+/// Snapshot of a ring of a Nethuns socket
+/// (see [`NethunsSocket::dump_rings`](crate::sockets::NethunsSocket::dump_rings)).
+#[derive(Clone, Debug, PartialEq, Eq, CopyGetters)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RingDump {
+    /// Number of slots of the ring
+    #[getset(get_copy = "pub")]
+    size: usize,
+    /// Head index of the ring
+    #[getset(get_copy = "pub")]
+    head: usize,
+    /// Tail index of the ring
+    #[getset(get_copy = "pub")]
+    tail: usize,
+    /// Status of each slot of the ring
+    slots: Vec<RingSlotStatus>,
+}
+
+impl RingDump {
+    /// Status of each slot of the ring
+    #[inline(always)]
+    pub fn slots(&self) -> &[RingSlotStatus] {
+        &self.slots
+    }
+    
+    /// Number of slots of the ring with the given status
+    pub fn count(&self, status: RingSlotStatus) -> usize {
+        self.slots.iter().filter(|&&s| s == status).count()
+    }
+}
+
+
+/// Snapshot of the rings of a Nethuns socket
+/// (see [`NethunsSocket::dump_rings`](crate::sockets::NethunsSocket::dump_rings)).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RingsDump {
+    /// RX ring, if the socket is in RX mode
+    pub rx: Option<RingDump>,
+    /// TX ring, if the socket is in TX mode
+    pub tx: Option<RingDump>,
+}
+
+
+/// Kind of ring of a Nethuns socket
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RingKind {
+    /// Receive ring
+    Rx,
+    /// Transmit ring
+    Tx,
+}
+
+
 /// Status of a ring slot
-#[derive(Debug, Default, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Eq, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[repr(u8)]
 pub enum RingSlotStatus {
     /// Not in use
     #[default]
@@ -233,6 +357,34 @@ impl AtomicRingSlotStatus {
     pub fn store(&self, val: RingSlotStatus, order: Ordering) {
         self.0.store(Self::to_u8(val), order)
     }
+    
+    /// Stores a value into the atomic if the current value is the same as
+    /// the `current` value.
+    ///
+    /// The return value is a result indicating whether the new value was
+    /// written and containing the previous value. On success this value
+    /// is guaranteed to be equal to `current`.
+    ///
+    /// See [`AtomicU8::compare_exchange`] for the meaning
+    /// of the `success` and `failure` orderings.
+    #[cfg(test)]
+    pub fn compare_exchange(
+        &self,
+        current: RingSlotStatus,
+        new: RingSlotStatus,
+        success: Ordering,
+        failure: Ordering,
+    ) -> Result<RingSlotStatus, RingSlotStatus> {
+        self.0
+            .compare_exchange(
+                Self::to_u8(current),
+                Self::to_u8(new),
+                success,
+                failure,
+            )
+            .map(Self::from_u8)
+            .map_err(Self::from_u8)
+    }
 }
 
 impl From<RingSlotStatus> for AtomicRingSlotStatus {
@@ -277,3 +429,101 @@ macro_rules! nethuns_ring_free_slots {
     };
 }
 pub(super) use nethuns_ring_free_slots;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_atomic_ring_slot_status_transitions() {
+        let status = AtomicRingSlotStatus::default();
+        assert_eq!(status.load(Ordering::Acquire), RingSlotStatus::Free);
+        
+        // Free -> InUse (packet received or marked for sending)
+        status.store(RingSlotStatus::InUse, Ordering::Release);
+        assert_eq!(status.load(Ordering::Acquire), RingSlotStatus::InUse);
+        
+        // InUse -> InFlight (flush in progress)
+        assert_eq!(
+            status.compare_exchange(
+                RingSlotStatus::InUse,
+                RingSlotStatus::InFlight,
+                Ordering::AcqRel,
+                Ordering::Acquire
+            ),
+            Ok(RingSlotStatus::InUse)
+        );
+        assert_eq!(status.load(Ordering::Acquire), RingSlotStatus::InFlight);
+        
+        // InFlight -> InUse must fail if the expected value is wrong
+        assert_eq!(
+            status.compare_exchange(
+                RingSlotStatus::Free,
+                RingSlotStatus::InUse,
+                Ordering::AcqRel,
+                Ordering::Acquire
+            ),
+            Err(RingSlotStatus::InFlight)
+        );
+        
+        // InFlight -> Free (transmission completed)
+        assert_eq!(
+            status.compare_exchange(
+                RingSlotStatus::InFlight,
+                RingSlotStatus::Free,
+                Ordering::AcqRel,
+                Ordering::Acquire
+            ),
+            Ok(RingSlotStatus::InFlight)
+        );
+        assert_eq!(status.load(Ordering::Acquire), RingSlotStatus::Free);
+    }
+    
+    #[test]
+    fn test_ring_slot_status_repr() {
+        for val in [
+            RingSlotStatus::Free,
+            RingSlotStatus::InUse,
+            RingSlotStatus::InFlight,
+        ] {
+            assert_eq!(AtomicRingSlotStatus::from_u8(val as u8), val);
+        }
+    }
+    
+    #[test]
+    fn test_ring_wrap_count() {
+        let mut ring = NethunsRing::new(4, 64);
+        for _ in 0..3 {
+            ring.rings_mut().advance_head();
+        }
+        assert_eq!(ring.wrap_count(), 0);
+        ring.rings_mut().advance_head();
+        assert_eq!(ring.wrap_count(), 1);
+        for _ in 0..9 {
+            ring.rings_mut().advance_head();
+        }
+        assert_eq!(ring.wrap_count(), 3);
+    }
+    
+    #[test]
+    #[cfg(debug_assertions)]
+    fn test_ring_check_invariants() {
+        let mut ring = NethunsRing::new(4, 64);
+        assert_eq!(ring.check_invariants(RingKind::Rx), Ok(()));
+        
+        // Packet received and given to the application
+        ring.get_slot(0).status.store(RingSlotStatus::InUse, Ordering::Release);
+        ring.rings_mut().advance_head();
+        assert_eq!(ring.check_invariants(RingKind::Rx), Ok(()));
+        
+        // Slot in use past the head
+        ring.get_slot(2).status.store(RingSlotStatus::InUse, Ordering::Release);
+        assert!(ring.check_invariants(RingKind::Rx).is_err());
+        
+        // Tail ahead of the head of a TX ring
+        let mut ring = NethunsRing::new(4, 64);
+        ring.rings_mut().advance_head();
+        assert!(ring.check_invariants(RingKind::Tx).is_err());
+    }
+}
@@ -0,0 +1,185 @@
+//! Event multiplexer over several Nethuns sockets.
+
+use std::time::{Duration, Instant};
+use std::{cmp, io, thread};
+
+use super::errors::NethunsRecvError;
+use super::{NethunsSocket, RecvPacket};
+
+
+/// Interval between two checks of the sockets with packets queued
+/// below their `rx_watermark`.
+const WATERMARK_RECHECK: Duration = Duration::from_micros(100);
+
+
+/// Set of [`NethunsSocket`]s which can be monitored at once
+/// for incoming packets, without requiring an async runtime.
+///
+/// It allows to capture packets from several interfaces/queues
+/// in the same thread, by waiting on all the sockets with a single
+/// `poll` system call instead of spinning on each socket.
+#[derive(Debug, Default)]
+pub struct SocketSet {
+    sockets: Vec<NethunsSocket>,
+}
+
+static_assertions::assert_impl_all!(SocketSet: Send);
+static_assertions::assert_not_impl_any!(SocketSet: Sync);
+
+impl SocketSet {
+    /// Create a new empty `SocketSet`.
+    #[inline(always)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Add a socket to the set.
+    ///
+    /// # Returns
+    /// The index of the socket inside the set.
+    #[inline(always)]
+    pub fn push(&mut self, socket: NethunsSocket) -> usize {
+        self.sockets.push(socket);
+        self.sockets.len() - 1
+    }
+    
+    /// Get the number of sockets in the set.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.sockets.len()
+    }
+    
+    /// Check if the set contains no sockets.
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.sockets.is_empty()
+    }
+    
+    /// Get a reference to the socket at index `idx`, if any.
+    #[inline(always)]
+    pub fn get(&self, idx: usize) -> Option<&NethunsSocket> {
+        self.sockets.get(idx)
+    }
+    
+    /// Consume the set and return the owned sockets.
+    #[inline(always)]
+    pub fn into_inner(self) -> Vec<NethunsSocket> {
+        self.sockets
+    }
+    
+    
+    /// Wait until at least one of the sockets in RX mode has packets
+    /// ready to be received, or until `timeout` expires.
+    ///
+    /// A socket is ready when at least `rx_watermark` packets are queued
+    /// (see [`NethunsSocketOptions::rx_watermark`](crate::types::NethunsSocketOptions::rx_watermark)).
+    /// When the timeout expires, the sockets with fewer packets queued
+    /// are reported as well.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<usize>)` - The indexes of the sockets with packets ready. The vector is empty if the timeout expired.
+    /// * `Err(io::Error)` - If the `poll` system call failed.
+    pub fn poll(&self, timeout: Duration) -> io::Result<Vec<usize>> {
+        let deadline = Instant::now() + timeout;
+        let mut wait = timeout;
+        
+        loop {
+            let readable = self.poll_readable(wait)?;
+            
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(readable);
+            }
+            let ready: Vec<usize> = readable
+                .iter()
+                .copied()
+                .filter(|&idx| self.above_watermark(idx))
+                .collect();
+            if !ready.is_empty() {
+                return Ok(ready);
+            }
+            
+            // Some sockets have packets queued below their watermark:
+            // wait for more packets to arrive
+            if !readable.is_empty() {
+                thread::sleep(cmp::min(WATERMARK_RECHECK, deadline - now));
+            }
+            wait = deadline.saturating_duration_since(Instant::now());
+        }
+    }
+    
+    
+    /// Call `poll` on the sockets in RX mode.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<usize>)` - The indexes of the sockets with at least one packet queued.
+    /// * `Err(io::Error)` - If the `poll` system call failed.
+    fn poll_readable(&self, timeout: Duration) -> io::Result<Vec<usize>> {
+        let mut fds: Vec<libc::pollfd> = self
+            .sockets
+            .iter()
+            .map(|s| libc::pollfd {
+                fd: s.fd(),
+                events: if s.rx() { libc::POLLIN } else { 0 },
+                revents: 0,
+            })
+            .collect();
+        
+        let timeout_ms =
+            cmp::min(timeout.as_millis(), libc::c_int::MAX as u128) as _;
+        
+        let ret = unsafe {
+            libc::poll(fds.as_mut_ptr(), fds.len() as _, timeout_ms)
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        
+        Ok(fds
+            .iter()
+            .enumerate()
+            .filter(|(_, fd)| fd.revents & libc::POLLIN != 0)
+            .map(|(idx, _)| idx)
+            .collect())
+    }
+    
+    
+    /// Check if the socket at index `idx` has at least `rx_watermark`
+    /// packets queued.
+    #[inline(always)]
+    fn above_watermark(&self, idx: usize) -> bool {
+        let socket = &self.sockets[idx];
+        match socket.base().opt.rx_watermark {
+            0 | 1 => true,
+            watermark => socket.rx_queued() >= watermark as usize,
+        }
+    }
+    
+    
+    /// Get the next unprocessed received packet from the socket
+    /// at index `idx` (see [`NethunsSocket::recv`]).
+    ///
+    /// # Returns
+    /// * `Ok(RecvPacket)` - The unprocessed received packet, if no error occurred.
+    /// * `Err(NethunsRecvError::Error)` - If `idx` is out of bounds.
+    /// * `Err(NethunsRecvError)` - Any other error returned by [`NethunsSocket::recv`].
+    #[inline(always)]
+    pub fn recv(&self, idx: usize) -> Result<RecvPacket, NethunsRecvError> {
+        self.sockets
+            .get(idx)
+            .ok_or_else(|| {
+                NethunsRecvError::Error(format!(
+                    "socket index {idx} out of bounds ({})",
+                    self.sockets.len()
+                ))
+            })?
+            .recv()
+    }
+}
+
+
+impl From<Vec<NethunsSocket>> for SocketSet {
+    fn from(sockets: Vec<NethunsSocket>) -> Self {
+        Self { sockets }
+    }
+}
@@ -1,5 +1,11 @@
 //! Utility structs and enums for the nethuns library.
 
+use std::fmt::{self, Display};
+use std::ops::Sub;
+use std::path::PathBuf;
+
+use bitflags::bitflags;
+use etherparse::{SlicedPacket, TransportSlice};
 use getset::CopyGetters;
 
 use crate::sockets::PkthdrTrait;
@@ -7,8 +13,29 @@ use crate::sockets::PkthdrTrait;
 
 /// Closure type for the filtering of received packets.
 /// Returns true if the packet should be received, false if it should be discarded.
+///
+/// If the closure panics, the panic is caught and the packet is dropped
+/// (see [`NethunsRecvError::FilterPanicked`](crate::sockets::errors::NethunsRecvError::FilterPanicked)).
+/// The closure should be unwind safe, since the state it captures
+/// is still used by the following calls after a panic.
 pub type NethunsFilter = dyn Fn(&dyn PkthdrTrait, &[u8]) -> bool + Send;
 
+/// Closure type for the filtering of received packets,
+/// which also receives the RSS hash of the packet computed by the NIC.
+///
+/// The hash is `None` if the `rxhash` option of the socket is disabled
+/// or if the underlying I/O framework doesn't provide it
+/// (e.g. netmap, which doesn't expose the RSS hash).
+/// Returns true if the packet should be received, false if it should be discarded.
+pub type NethunsFilterWithHash =
+    dyn Fn(&dyn PkthdrTrait, &[u8], Option<u32>) -> bool + Send;
+
+/// Closure type for observing the raw bytes of the received packets
+/// (see [`NethunsSocket::set_tap`](crate::sockets::NethunsSocket::set_tap)).
+///
+/// Unlike a [`NethunsFilter`], a tap can't discard packets.
+pub type NethunsTap = dyn Fn(&[u8]) + Send;
+
 
 /// Enum for specifying which queue of the device should be used
 /// for capturing packets.
@@ -19,8 +46,44 @@ pub enum NethunsQueue {
     Some(u32),
 }
 
+impl NethunsQueue {
+    /// Get the queue with index `idx`, or any queue if `None`.
+    #[inline(always)]
+    pub fn from_option(idx: Option<u32>) -> Self {
+        match idx {
+            Some(idx) => Self::Some(idx),
+            None => Self::Any,
+        }
+    }
+    
+    /// Get the index of the queue, or `None` for any queue.
+    #[inline(always)]
+    pub fn as_option(&self) -> Option<u32> {
+        match self {
+            Self::Some(idx) => Some(*idx),
+            Self::Any => None,
+        }
+    }
+}
+
+/// Print the index of the queue, or `any`.
+impl Display for NethunsQueue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Some(idx) => write!(f, "{idx}"),
+            Self::Any => write!(f, "any"),
+        }
+    }
+}
+
 
 /// Enum for specifying the direction for capturing packets.
+///
+/// With netmap, the packets transmitted by the host stack (out direction)
+/// are captured from the host rings of the device, which are opened
+/// only if the `host_rings` option is set and the socket is bound
+/// to any queue ([`NethunsQueue::Any`]). Otherwise, only the hardware
+/// rings are opened, whatever the direction.
 #[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Eq, Ord)]
 pub enum NethunsCaptureDir {
     In,
@@ -30,6 +93,18 @@ pub enum NethunsCaptureDir {
 }
 
 
+/// Direction of a captured packet
+/// (see [`RecvPacket::direction`](crate::sockets::RecvPacket::direction)).
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum PacketDirection {
+    /// Packet received from the network.
+    #[default]
+    In,
+    /// Packet transmitted by the local host.
+    Out,
+}
+
+
 /// Enum for specifying the mode for capturing packets.
 #[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Eq, Ord)]
 pub enum NethunsCaptureMode {
@@ -37,6 +112,11 @@ pub enum NethunsCaptureMode {
     Default,
     SkbMode,
     DrvMode,
+    /// Received packets are borrowed from the ring slots,
+    /// which are released only when the packets are dropped.
+    ///
+    /// To release the slots immediately, copy the packets into owned
+    /// buffers with [`NethunsSocket::recv_owned`](crate::sockets::NethunsSocket::recv_owned).
     ZeroCopy,
 }
 
@@ -51,19 +131,180 @@ pub enum NethunsSocketMode {
 }
 
 
+/// Enum for specifying how often the rings of the socket should be
+/// synchronized with the underlying I/O framework
+/// (e.g. by means of the `NIOCRXSYNC`/`NIOCTXSYNC` ioctls in netmap).
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Eq, Ord)]
+pub enum NethunsRingSyncPolicy {
+    /// Synchronize the rings at every `recv`/`flush` call.
+    SyncEveryCall,
+    /// Synchronize the RX rings only when all of them appear empty,
+    /// and the TX rings at every `flush` call.
+    #[default]
+    SyncOnEmpty,
+    /// Synchronize the rings once every N `recv`/`flush` calls.
+    SyncEveryN(u32),
+}
+
+
+/// I/O framework used by the sockets, selected at compile time
+/// through the cargo features (see [`active_backend`](crate::active_backend)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Backend {
+    Netmap,
+    AfXdp,
+    AfPacket,
+    Pcap,
+}
+
+/// Print the name of the backend (e.g. `netmap`).
+impl Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Netmap => "netmap",
+            Self::AfXdp => "af_xdp",
+            Self::AfPacket => "af_packet",
+            Self::Pcap => "pcap",
+        };
+        write!(f, "{name}")
+    }
+}
+
+
+/// Byte order of the pcap files written by a
+/// [`NethunsSocketPcap`](crate::sockets::pcap::NethunsSocketPcap).
+///
+/// Readers detect the byte order from the magic number of the file,
+/// so a specific order is only needed to reproduce the output
+/// of another capture tool or architecture exactly.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Eq, Ord)]
+pub enum PcapByteOrder {
+    /// Byte order of the host.
+    #[default]
+    Native,
+    LittleEndian,
+    BigEndian,
+}
+
+impl PcapByteOrder {
+    /// Check if the byte order is the opposite of the one of the host.
+    #[inline(always)]
+    pub fn is_swapped(&self) -> bool {
+        match self {
+            Self::Native => false,
+            Self::LittleEndian => cfg!(target_endian = "big"),
+            Self::BigEndian => cfg!(target_endian = "little"),
+        }
+    }
+}
+
+
 /// Options for the nethuns socket.
 #[derive(Clone, Debug, Default, PartialEq, PartialOrd, Eq, Ord)]
 pub struct NethunsSocketOptions {
     pub numblocks: u32,
     pub numpackets: u32,
     pub packetsize: u32,
+    /// Number of slots of the RX ring. If set, it takes precedence
+    /// over `numblocks * numpackets`, which is used otherwise.
+    pub rx_ring_size: Option<u32>,
+    /// Number of slots of the TX ring. If set, it takes precedence
+    /// over `numblocks * numpackets`, which is used otherwise.
+    pub tx_ring_size: Option<u32>,
+    /// Maximum number of bytes retained for each packet.
+    /// If `None`, packets are truncated to `packetsize` bytes.
+    pub snaplen: Option<u32>,
     pub timeout_ms: u32,
     pub dir: NethunsCaptureDir,
+    /// Open the host rings of the device too (netmap only), to capture
+    /// the packets transmitted by the host stack according to `dir`.
+    /// It requires binding to any queue, and the socket takes traffic
+    /// from the host stack and needs one more ring of extra buffers.
+    /// Default: off.
+    pub host_rings: bool,
     pub capture: NethunsCaptureMode,
     pub mode: NethunsSocketMode,
     pub promisc: bool,
     pub rxhash: bool,
+    /// Bypass the qdisc layer of the kernel when transmitting packets.
+    ///
+    /// It has no effect on netmap, which always bypasses the qdisc layer,
+    /// nor on sockets not in TX mode (a warning is emitted in the latter case).
     pub tx_qdisc_bypass: bool,
+    pub ring_sync_policy: NethunsRingSyncPolicy,
+    /// Number of additional buffers requested to the I/O framework
+    /// on top of the ones required by the rings (RX only).
+    /// They provide headroom for receiving bursts of packets.
+    pub extra_buffers: u32,
+    /// Classify the received packets by L4 protocol
+    /// (see [`NethunsSocket::proto_histogram`](crate::sockets::NethunsSocket::proto_histogram)).
+    pub classify: bool,
+    /// Fail to open the socket, instead of just printing a warning,
+    /// if the estimated memory required by the socket exceeds
+    /// the current `RLIMIT_MEMLOCK` (see [`NethunsSocketOptions::estimated_memory`]).
+    pub strict_memlock: bool,
+    /// Zero the bytes of the slot buffers beyond `caplen` after each
+    /// `recv`/`read`, so that the contents of previous packets never leak
+    /// (e.g. into a pcap file). It has a per-packet cost, so it's off by default.
+    pub zero_slots: bool,
+    /// Bring the device up (i.e. set `IFF_UP`) at bind time, if it isn't
+    /// already, and restore its previous state when the socket is dropped.
+    /// It requires the `CAP_NET_ADMIN` capability.
+    pub interface_up: bool,
+    /// Minimum number of packets which must be queued in the RX rings
+    /// before the socket is reported as readable by
+    /// [`SocketSet::poll`](crate::sockets::SocketSet::poll).
+    ///
+    /// Values above 1 batch the wakeups, trading latency for throughput:
+    /// a packet may wait in the ring until enough packets arrive
+    /// or the timeout of the `poll` expires.
+    /// `0` and `1` (default) report the socket as soon as
+    /// a single packet is queued.
+    pub rx_watermark: u32,
+    /// Prefetch the buffer of the next received packet into the CPU
+    /// cache while the current one is being processed (x86_64 only).
+    /// It improves the throughput of tight capture loops, but it wastes
+    /// memory bandwidth when packets are received one at a time.
+    /// Default: off.
+    pub prefetch: bool,
+    /// Network namespace of the device (e.g. `/var/run/netns/blue`),
+    /// or `None` for the namespace of the calling thread.
+    ///
+    /// The device is bound and configured (e.g. promisc mode,
+    /// hardware flow rules) from temporary threads which enter
+    /// the namespace, so the namespace of the calling thread is never
+    /// changed. It requires the `CAP_SYS_ADMIN` capability.
+    pub netns: Option<PathBuf>,
+    /// Remove the filter set on the socket if it panics, so that
+    /// the following packets are received without filtering.
+    /// Otherwise, the filter is kept and called again for the next packet.
+    pub disable_filter_on_panic: bool,
+    /// Verify the IPv4/TCP/UDP checksums of the packets read from a pcap
+    /// file, counting the corrupt ones (see
+    /// [`NethunsSocketPcap::reader_stats`](crate::sockets::pcap::NethunsSocketPcap::reader_stats)).
+    /// It's expensive, so it's off by default.
+    pub verify_checksums: bool,
+    /// Maximum number of packets pushed to the TX rings by a single
+    /// call to [`NethunsSocket::flush`](crate::sockets::NethunsSocket::flush),
+    /// bounding its duration on a saturated ring. The remaining packets
+    /// stay queued up for the following calls.
+    /// `None` (default) pushes all the queued up packets.
+    pub max_tx_batch: Option<usize>,
+    /// Suppress each received packet byte-identical to the previous one,
+    /// counting it in [`NethunsStat::deduped`] as well as in
+    /// [`NethunsStat::filtered`] (e.g. on links flooded
+    /// by keepalives). Only *consecutive* duplicates are collapsed,
+    /// not reordered ones. Default: off.
+    pub dedup: bool,
+    /// Byte order of the pcap files opened in writing mode
+    /// (see [`PcapByteOrder`]). Default: the byte order of the host.
+    pub pcap_byte_order: PcapByteOrder,
+    /// Fail to open a pcap file for reading if its snaplen is greater
+    /// than `packetsize`, instead of silently truncating the tails
+    /// of the longest packets (see
+    /// [`NethunsSocketPcap::file_snaplen`](crate::sockets::pcap::NethunsSocketPcap::file_snaplen)).
+    /// Default: off.
+    pub strict_snaplen: bool,
     
     /// xdp only
     pub xdp_prog: Option<String>,
@@ -77,6 +318,139 @@ pub struct NethunsSocketOptions {
     pub pin_dir: Option<String>,
 }
 
+impl NethunsSocketOptions {
+    /// Options for passive traffic capture (e.g. monitoring tools),
+    /// which can be tweaked through the struct update syntax.
+    ///
+    /// RX only socket in promiscuous mode, with a 4096-slot ring of
+    /// 2048-byte buffers (enough for standard MTU frames), balancing
+    /// memory usage and tolerance to traffic bursts.
+    ///
+    /// With netmap, the socket takes 4096 buffers from the netmap buffer
+    /// pool for each RX queue it receives from, one for each ring slot.
+    pub fn capture_preset() -> Self {
+        Self {
+            numblocks: 1,
+            numpackets: 4096,
+            packetsize: 2048,
+            mode: NethunsSocketMode::RxOnly,
+            promisc: true,
+            ..Default::default()
+        }
+    }
+    
+    /// Options optimized for throughput (e.g. packet forwarding
+    /// at line rate), at the cost of memory and latency.
+    ///
+    /// Large rings (8192 slots) and extra buffers absorb long bursts,
+    /// the buffer of the next packet is prefetched, the rings are
+    /// synchronized only when empty and the wakeups of
+    /// [`SocketSet::poll`](crate::sockets::SocketSet::poll) are batched,
+    /// minimizing the system calls per packet.
+    ///
+    /// With netmap, binding to any queue takes 8192 buffers for each
+    /// RX queue, 4096 extra buffers and 8192 buffers for the TX ring,
+    /// i.e. `(number of RX queues + 1) * 8192 + 4096` buffers from
+    /// the netmap buffer pool, on top of the ones of the NIC rings.
+    /// The default pool (163840 buffers) fits devices with up to about
+    /// 12 queues: larger devices need a bigger pool (`netmap.buf_num`
+    /// module parameter) or binding to a single queue.
+    pub fn high_throughput_preset() -> Self {
+        Self {
+            numblocks: 1,
+            numpackets: 8192,
+            packetsize: 2048,
+            mode: NethunsSocketMode::RxTx,
+            ring_sync_policy: NethunsRingSyncPolicy::SyncOnEmpty,
+            extra_buffers: 4096,
+            rx_watermark: 64,
+            prefetch: true,
+            ..Default::default()
+        }
+    }
+    
+    /// Options optimized for latency (e.g. request/response
+    /// applications), at the cost of throughput.
+    ///
+    /// Small rings (256 slots) keep the working set in cache,
+    /// no buffer is prefetched, the rings are synchronized at every call,
+    /// the socket is reported readable as soon as a packet arrives
+    /// and each `flush` is bounded to 32 packets.
+    pub fn low_latency_preset() -> Self {
+        Self {
+            numblocks: 1,
+            numpackets: 256,
+            packetsize: 2048,
+            mode: NethunsSocketMode::RxTx,
+            prefetch: false,
+            ring_sync_policy: NethunsRingSyncPolicy::SyncEveryCall,
+            rx_watermark: 1,
+            max_tx_batch: Some(32),
+            ..Default::default()
+        }
+    }
+    
+    /// Get the maximum number of bytes retained for each packet,
+    /// i.e. `snaplen` capped to `packetsize`.
+    #[inline(always)]
+    pub fn effective_snaplen(&self) -> u32 {
+        match self.snaplen {
+            Some(snaplen) => snaplen.min(self.packetsize),
+            None => self.packetsize,
+        }
+    }
+    
+    /// Get the number of slots of the RX ring, i.e. `rx_ring_size`
+    /// if set, `numblocks * numpackets` otherwise.
+    #[inline(always)]
+    pub fn rx_ring_slots(&self) -> usize {
+        self.ring_slots(self.rx_ring_size)
+    }
+    
+    /// Get the number of slots of the TX ring, i.e. `tx_ring_size`
+    /// if set, `numblocks * numpackets` otherwise.
+    #[inline(always)]
+    pub fn tx_ring_slots(&self) -> usize {
+        self.ring_slots(self.tx_ring_size)
+    }
+    
+    fn ring_slots(&self, size: Option<u32>) -> usize {
+        match size {
+            Some(size) => size as usize,
+            None => (self.numblocks as usize)
+                .saturating_mul(self.numpackets as usize),
+        }
+    }
+    
+    /// Get an estimate of the memory (in bytes) required by the rings
+    /// of a socket opened with these options.
+    ///
+    /// Each ring allocates [`rx_ring_slots`](Self::rx_ring_slots) or
+    /// [`tx_ring_slots`](Self::tx_ring_slots) slots
+    /// (rounded up to the next power of two), each of `packetsize` bytes.
+    pub fn estimated_memory(&self) -> usize {
+        let ring_memory = |nslots: usize| {
+            nslots
+                .checked_next_power_of_two()
+                .unwrap_or(usize::MAX)
+                .saturating_mul(self.packetsize as usize)
+        };
+        let rx = match self.mode {
+            NethunsSocketMode::RxTx | NethunsSocketMode::RxOnly => {
+                ring_memory(self.rx_ring_slots())
+            }
+            NethunsSocketMode::TxOnly => 0,
+        };
+        let tx = match self.mode {
+            NethunsSocketMode::RxTx | NethunsSocketMode::TxOnly => {
+                ring_memory(self.tx_ring_slots())
+            }
+            NethunsSocketMode::RxOnly => 0,
+        };
+        rx.saturating_add(tx)
+    }
+}
+
 
 /// Statistics for the nethuns socket.
 #[derive(
@@ -84,13 +458,307 @@ pub struct NethunsSocketOptions {
 )]
 #[getset(get_copy = "pub")]
 pub struct NethunsStat {
-    rx_packets: u64,
-    tx_packets: u64,
-    rx_dropped: u64,
-    rx_if_dropped: u64,
+    pub(crate) rx_packets: u64,
+    pub(crate) tx_packets: u64,
+    pub(crate) rx_dropped: u64,
+    pub(crate) rx_if_dropped: u64,
     /// xdp only
-    rx_invalid: u64,
+    pub(crate) rx_invalid: u64,
     /// xdp only
-    tx_invalid: u64,
-    freeze: u64,
+    pub(crate) tx_invalid: u64,
+    /// Number of times the RX ring was frozen, i.e. the kernel couldn't
+    /// deliver packets because all the ring slots were still owned
+    /// by the application. Freezes are detected only by the calls to
+    /// [`NethunsSocket::is_frozen`](crate::sockets::NethunsSocket::is_frozen),
+    /// and each one is counted once.
+    pub(crate) freeze: u64,
+    /// Calls to `recv` which failed with `InUse` because the head slot
+    /// of the RX ring was still in use by the application (ring full).
+    /// It counts the failed calls, not lost packets: the packets stay
+    /// in the ring until the slot is released.
+    pub(crate) app_dropped: u64,
+    /// Packets discarded by the filter set on the socket,
+    /// or suppressed as duplicates (also counted in `deduped`).
+    pub(crate) filtered: u64,
+    /// Packets suppressed as duplicates of the previous one
+    /// (see the `dedup` option).
+    pub(crate) deduped: u64,
+}
+
+
+/// Per-field difference between two snapshots of the statistics,
+/// e.g. to compute rates: `let delta = &now - &prev;`.
+///
+/// Each difference saturates at zero, so a counter which wrapped around
+/// or was reset between the two snapshots yields 0 instead of
+/// a huge bogus value.
+impl Sub for &NethunsStat {
+    type Output = NethunsStat;
+    
+    fn sub(self, prev: Self) -> NethunsStat {
+        NethunsStat {
+            rx_packets: self.rx_packets.saturating_sub(prev.rx_packets),
+            tx_packets: self.tx_packets.saturating_sub(prev.tx_packets),
+            rx_dropped: self.rx_dropped.saturating_sub(prev.rx_dropped),
+            rx_if_dropped: self
+                .rx_if_dropped
+                .saturating_sub(prev.rx_if_dropped),
+            rx_invalid: self.rx_invalid.saturating_sub(prev.rx_invalid),
+            tx_invalid: self.tx_invalid.saturating_sub(prev.tx_invalid),
+            freeze: self.freeze.saturating_sub(prev.freeze),
+            app_dropped: self.app_dropped.saturating_sub(prev.app_dropped),
+            filtered: self.filtered.saturating_sub(prev.filtered),
+            deduped: self.deduped.saturating_sub(prev.deduped),
+        }
+    }
+}
+
+
+/// Features supported by the I/O framework of a socket
+/// (see [`NethunsSocket::capabilities`](crate::sockets::NethunsSocket::capabilities)).
+///
+/// A supported feature may still fail at runtime if the driver
+/// of the device doesn't implement it (e.g. hardware flow rules).
+#[derive(Clone, Copy, CopyGetters, Debug, Default, PartialEq, Eq, Hash)]
+#[getset(get_copy = "pub")]
+pub struct Capabilities {
+    /// Fanout of the packets among a group of sockets
+    /// ([`NethunsSocket::fanout`](crate::sockets::NethunsSocket::fanout)).
+    pub(crate) fanout: bool,
+    /// Forwarding of a received packet without copying its payload.
+    pub(crate) zero_copy_forward: bool,
+    /// Timestamps of the received packets taken by the NIC.
+    pub(crate) hw_timestamps: bool,
+    /// Statistics of the socket
+    /// ([`NethunsSocket::stats`](crate::sockets::NethunsSocket::stats)).
+    pub(crate) stats: bool,
+    /// Hardware flow rules
+    /// ([`NethunsSocket::add_flow_rule`](crate::sockets::NethunsSocket::add_flow_rule)).
+    pub(crate) flow_rules: bool,
+    /// In-kernel BPF filters
+    /// ([`NethunsSocket::attach_bpf`](crate::sockets::NethunsSocket::attach_bpf)).
+    pub(crate) bpf_filters: bool,
+}
+
+
+bitflags! {
+    /// Flags of a netmap slot (`NS_*` constants from `net/netmap.h`).
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct NetmapFlags: u16 {
+        /// The buffer of the slot has been changed.
+        const BUF_CHANGED = 0x0001;
+        /// Ask the hardware to report the completion of the transmission.
+        const REPORT = 0x0002;
+        /// Pass the packet to the other endpoint (host stack or NIC).
+        const FORWARD = 0x0004;
+        /// Don't learn the source port of the packet (VALE switch).
+        const NO_LEARN = 0x0008;
+        /// The slot contains a pointer to the data, not the data itself.
+        const INDIRECT = 0x0010;
+        /// The packet continues in the next slot.
+        const MOREFRAG = 0x0020;
+    }
+}
+
+
+/// Number of times the slots of the rings have been reused,
+/// i.e. have completed a Free → InUse → Free cycle, since the socket
+/// was opened or since the last reset
+/// (see [`NethunsSocket::slot_turnover`](crate::sockets::NethunsSocket::slot_turnover)).
+///
+/// A high turnover along with frequent `NoPacketsAvailable`/`InUse`
+/// errors suggests that the rings are undersized.
+#[derive(
+    Clone, Copy, CopyGetters, Debug, Default, PartialEq, PartialOrd, Eq, Ord,
+)]
+#[getset(get_copy = "pub")]
+pub struct TurnoverStats {
+    /// Number of slot cycles of the RX ring.
+    rx_cycles: u64,
+    /// Number of slot cycles of the TX ring.
+    tx_cycles: u64,
+    /// Number of slots of the RX ring (0 if not in RX mode).
+    rx_size: usize,
+    /// Number of slots of the TX ring (0 if not in TX mode).
+    tx_size: usize,
+}
+
+impl TurnoverStats {
+    pub(crate) fn new(
+        rx_cycles: u64,
+        tx_cycles: u64,
+        rx_size: usize,
+        tx_size: usize,
+    ) -> Self {
+        Self {
+            rx_cycles,
+            tx_cycles,
+            rx_size,
+            tx_size,
+        }
+    }
+    
+    /// Average number of times each slot of the RX ring has been reused.
+    #[inline(always)]
+    pub fn rx_turnover(&self) -> f64 {
+        turnover(self.rx_cycles, self.rx_size)
+    }
+    
+    /// Average number of times each slot of the TX ring has been reused.
+    #[inline(always)]
+    pub fn tx_turnover(&self) -> f64 {
+        turnover(self.tx_cycles, self.tx_size)
+    }
+}
+
+#[inline(always)]
+fn turnover(cycles: u64, size: usize) -> f64 {
+    if size == 0 {
+        0.0
+    } else {
+        cycles as f64 / size as f64
+    }
+}
+
+
+/// Number of received packets per L4 protocol.
+#[derive(
+    Clone, Copy, CopyGetters, Debug, Default, PartialEq, PartialOrd, Eq, Ord,
+)]
+#[getset(get_copy = "pub")]
+pub struct ProtoHistogram {
+    tcp: u64,
+    udp: u64,
+    /// ICMP and ICMPv6
+    icmp: u64,
+    other: u64,
+}
+
+impl ProtoHistogram {
+    /// Classify an Ethernet frame by its L4 protocol
+    /// and increment the corresponding counter.
+    pub(crate) fn count(&mut self, packet: &[u8]) {
+        match SlicedPacket::from_ethernet(packet).map(|p| p.transport) {
+            Ok(Some(TransportSlice::Tcp(_))) => self.tcp += 1,
+            Ok(Some(TransportSlice::Udp(_))) => self.udp += 1,
+            Ok(Some(TransportSlice::Icmpv4(_)))
+            | Ok(Some(TransportSlice::Icmpv6(_))) => self.icmp += 1,
+            _ => self.other += 1,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use etherparse::PacketBuilder;
+    
+    use super::*;
+    
+    #[test]
+    fn test_nethuns_queue_conversions() {
+        assert_eq!(NethunsQueue::from_option(Some(3)), NethunsQueue::Some(3));
+        assert_eq!(NethunsQueue::from_option(None), NethunsQueue::Any);
+        assert_eq!(NethunsQueue::Some(3).as_option(), Some(3));
+        assert_eq!(NethunsQueue::Any.as_option(), None);
+        assert_eq!(NethunsQueue::Some(3).to_string(), "3");
+        assert_eq!(NethunsQueue::Any.to_string(), "any");
+    }
+    
+    #[test]
+    fn test_stat_sub() {
+        let prev = NethunsStat {
+            rx_packets: 100,
+            rx_dropped: 5,
+            filtered: 50,
+            ..Default::default()
+        };
+        let now = NethunsStat {
+            rx_packets: 150,
+            rx_dropped: 7,
+            filtered: 10, // reset in the meantime
+            ..Default::default()
+        };
+        let delta = &now - &prev;
+        assert_eq!(delta.rx_packets(), 50);
+        assert_eq!(delta.rx_dropped(), 2);
+        assert_eq!(delta.filtered(), 0);
+        assert_eq!(delta.tx_packets(), 0);
+    }
+    
+    #[test]
+    fn test_presets() {
+        let capture = NethunsSocketOptions::capture_preset();
+        let throughput = NethunsSocketOptions::high_throughput_preset();
+        let latency = NethunsSocketOptions::low_latency_preset();
+        
+        assert_eq!(capture.mode, NethunsSocketMode::RxOnly);
+        assert!(latency.rx_ring_slots() < capture.rx_ring_slots());
+        assert!(capture.rx_ring_slots() < throughput.rx_ring_slots());
+        assert_eq!(throughput.estimated_memory(), 2 * 8192 * 2048);
+        assert!(throughput.prefetch && !latency.prefetch);
+    }
+    
+    #[test]
+    fn test_estimated_memory() {
+        let opt = NethunsSocketOptions {
+            numblocks: 1,
+            numpackets: 1000,
+            packetsize: 2048,
+            mode: NethunsSocketMode::RxTx,
+            ..Default::default()
+        };
+        assert_eq!(opt.estimated_memory(), 2 * 1024 * 2048);
+        
+        let opt = NethunsSocketOptions {
+            mode: NethunsSocketMode::RxOnly,
+            ..opt
+        };
+        assert_eq!(opt.estimated_memory(), 1024 * 2048);
+    }
+    
+    #[test]
+    fn test_ring_size_precedence() {
+        let opt = NethunsSocketOptions {
+            numblocks: 4,
+            numpackets: 256,
+            packetsize: 2048,
+            rx_ring_size: Some(4096),
+            mode: NethunsSocketMode::RxTx,
+            ..Default::default()
+        };
+        assert_eq!(opt.rx_ring_slots(), 4096);
+        assert_eq!(opt.tx_ring_slots(), 1024);
+        assert_eq!(opt.estimated_memory(), (4096 + 1024) * 2048);
+    }
+    
+    #[test]
+    fn test_proto_histogram_count() {
+        let payload = [1_u8, 2, 3, 4];
+        let mut histogram = ProtoHistogram::default();
+        
+        // UDP packet
+        let builder = PacketBuilder::ethernet2([1; 6], [2; 6])
+            .ipv4([192, 168, 1, 1], [192, 168, 1, 2], 20)
+            .udp(21, 1234);
+        let mut packet = Vec::with_capacity(builder.size(payload.len()));
+        builder.write(&mut packet, &payload).unwrap();
+        histogram.count(&packet);
+        
+        // TCP packet
+        let builder = PacketBuilder::ethernet2([1; 6], [2; 6])
+            .ipv4([192, 168, 1, 1], [192, 168, 1, 2], 20)
+            .tcp(21, 1234, 1, 4096);
+        let mut packet = Vec::with_capacity(builder.size(payload.len()));
+        builder.write(&mut packet, &payload).unwrap();
+        histogram.count(&packet);
+        
+        // Malformed packet
+        histogram.count(&[0xff; 4]);
+        
+        assert_eq!(histogram.udp(), 1);
+        assert_eq!(histogram.tcp(), 1);
+        assert_eq!(histogram.icmp(), 0);
+        assert_eq!(histogram.other(), 1);
+    }
 }